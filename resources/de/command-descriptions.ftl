@@ -1,10 +1,64 @@
 base = Passt deinen Willkommenssound an
 base-clear = Entfernt deinen Willkommenssound
+    .name = entfernen
 base-set = Setzt deinen Willkommenssound
+    .name = setzen
 base-set-file = Lädt eine Audiodatei als Willkommenssound hoch
+    .name = datei
 base-set-file-attachment = Füge eine Audiodatei an. Am besten .mp3!
+    .name = anhang
 base-set-url = Gibt dem Bot einen Link zu einer Audiodatei
+    .name = url
 base-set-url-link = Link zu einer Audiodatei im Internet. Endet im Idealfall mit '.mp3'!
+    .name = link
 base-admin = Bot-Konfiguration
+    .name = admin
 base-admin-forbid = Legt eine Rolle fest, deren Sounds nicht abgespielt werden
+    .name = verbieten
 base-admin-forbid-role = Die eindeutige, zu blockende Rolle
+    .name = rolle
+base-privacy = Verwalte die über dich gespeicherten Daten
+    .name = datenschutz
+base-privacy-export = Schickt dir alle über dich gespeicherten Daten
+    .name = exportieren
+base-privacy-delete = Löscht alle über dich gespeicherten Daten
+    .name = löschen
+base-admin-moderation = Neue Willkommenssounds erst nach Freigabe spielen
+    .name = moderation
+base-admin-moderation-channel = Kanal, in dem Willkommenssounds freigegeben werden, leer lassen zum Abschalten
+    .name = kanal
+base-admin-mute = Spielt den Willkommenssound einer Person hier nicht mehr
+    .name = stummschalten
+base-admin-mute-user = Die stummzuschaltende Person
+    .name = person
+base-admin-unmute = Spielt den Willkommenssound einer Person hier wieder
+    .name = entstummen
+base-admin-unmute-user = Die wieder zu hörende Person
+    .name = person
+base-admin-clear = Entfernt den aktuellen Willkommenssound einer Person hier
+    .name = entfernen
+base-admin-clear-user = Die Person, deren Willkommenssound entfernt wird
+    .name = person
+base-admin-cooldown = Begrenzt, wie oft Willkommenssounds gespielt werden
+    .name = wartezeit
+base-admin-cooldown-user = Sekunden bis zur Wiederholung je Person, 0 für unbegrenzt, leer für Standard
+    .name = person
+base-admin-cooldown-guild = Maximale Sounds pro Minute, 0 für unbegrenzt, leer für Standard
+    .name = server
+base-admin-log-channel = Legt einen Kanal fest, in dem Änderungen protokolliert werden
+    .name = protokollkanal
+base-admin-log-channel-channel = Kanal für das Protokoll, leer lassen zum Abschalten
+    .name = kanal
+base-admin-audit = Zeigt die letzten Änderungen an
+    .name = protokoll
+base-admin-audit-limit = Anzahl der Einträge, 10 wenn leer
+    .name = anzahl
+base-language = Legt deine Sprache für Antworten fest
+    .name = sprache
+base-language-locale = Sprache, leer lassen für automatische Wahl
+    .name = sprache
+base-admin-language = Legt die Standardsprache dieses Servers fest
+    .name = sprache
+base-admin-language-locale = Sprache, leer lassen für automatische Wahl
+    .name = sprache
+message-use-as-chime = Als Willkommenssound nutzen
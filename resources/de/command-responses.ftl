@@ -1,10 +1,72 @@
 success = Das hat funktioniert! 🎉
-fail = Das hat nicht funktioniert... 😟
-file-too-large = Das ist mir zu groß! 🥵
+command-outdated = Dieser Befehl ist veraltet, versuch es gleich nochmal! 🔄
+file-too-large = { $size ->
+    [unknown] Das ist mir zu groß, höchstens { $limit } KB bitte! 🥵
+   *[other] Das ist mir zu groß, { $size } KB sind mehr als { $limit } KB! 🥵
+}
 download-failed = Der Download ist abgekackt... 😟
 bad-url = Mit dieser URL kann ich nix anfangen. 🤔
-duration-exceeded = Das dauert mir zu lange! 🙄
+duration-exceeded = Das dauert mir zu lange, { $duration } Sekunden sind mehr als { $limit } Sekunden! 🙄
 data-unreadable = Diese Daten kann ich nicht entziffern. 🧐
 internal-error = Hoppla! Es ist ein interner Fehler aufgetreten... 🤕
 only-in-guilds = Das funktioniert nur auf Servern! ☝
 missing-permissions = Wer denkst du wer du bist? 👿
+privacy-export-message = Hier sind alle Daten, die ich über dich gespeichert habe. 📦
+privacy-export-sent = Schau in deine Direktnachrichten! 📬
+privacy-export-dm-failed = Ich kann dir keine Direktnachrichten schicken... 📪
+privacy-delete-confirm = Soll ich wirklich alle Daten löschen, die ich über dich gespeichert habe? Das kann nicht rückgängig gemacht werden! ⚠
+privacy-delete-button = Meine Daten löschen
+privacy-delete-done = Alle deine Daten wurden gelöscht. 🗑
+url-forbidden = Von dort darf ich nichts herunterladen! 🚫
+resolve-failed = Aus diesem Link krieg ich keinen Ton raus. 🙉
+no-audio-stream = Da ist ja gar kein Ton drin! 🔇
+unsupported-codec = { $codec } kann ich nicht abspielen, nur { $allowed }. 🤷
+unsupported-channels = { $channels } Kanäle hab ich nicht, höchstens { $max } bitte. 🎧
+unsupported-sample-rate = Eine Abtastrate von { $rate } Hz ist mir nicht geheuer, sie muss zwischen { $min } und { $max } Hz liegen. 📉
+video-not-allowed = Videos nehm ich nicht, nur Audio! 🎬
+chime-silent = Da hört man ja nix! 🤫
+chime-pending-approval = Gespeichert! Hier wird dein Willkommenssound erst gespielt, wenn die Moderation ihn abgesegnet hat. ⏳
+chime-still-pending = Gespeichert! Dieser Willkommenssound wartet hier noch auf die Freigabe durch die Moderation. ⏳
+chime-rejected = Gespeichert, aber die Moderation hat diesen Willkommenssound hier schon abgelehnt, er wird also nicht gespielt. 🚫
+moderation-enabled = Neue Willkommenssounds müssen jetzt erst in { $channel } abgesegnet werden. 🛂
+moderation-disabled = Willkommenssounds müssen nicht mehr abgesegnet werden. 🛃
+moderation-review = { $user } hat einen neuen Willkommenssound. Darf der gespielt werden?
+moderation-approve = Absegnen
+moderation-reject = Ablehnen
+moderation-approved = Der Willkommenssound wurde von { $moderator } abgesegnet. ✅
+moderation-rejected = Der Willkommenssound wurde von { $moderator } abgelehnt. ❌
+moderation-already-decided = Darüber wurde schon entschieden. 🤷
+role-forbidden = Willkommenssounds von Leuten mit der Rolle { $role } werden hier nicht mehr gespielt. 🚷
+cooldowns-set = { $user ->
+    [0] Willkommenssounds einer Person können jederzeit gespielt werden
+   *[other] Willkommenssounds einer Person werden höchstens alle { $user } Sekunden gespielt
+}, { $guild ->
+    [0] ohne Begrenzung für diesen Server. ⏱
+   *[other] und höchstens { $guild } pro Minute auf diesem Server. ⏱
+}
+user-muted = Der Willkommenssound von { $user } wird hier nicht mehr gespielt. 🔇
+user-unmuted = Der Willkommenssound von { $user } wird hier wieder gespielt. 🔊
+user-chime-cleared = Der aktuelle Willkommenssound von { $user } wird hier nicht mehr gespielt. 🗑
+user-has-no-chime = { $user } hat gar keinen Willkommenssound. 🤷
+rate-limited = Nicht so schnell! Versuch es { $retry } nochmal. ⏱
+no-audio-attachment = An dieser Nachricht hängt keine Audiodatei. 🔇
+audit-action = { $action ->
+    [forbid] hat die blockierte Rolle geändert
+    [moderation] hat den Moderationskanal geändert
+    [cooldown] hat die Wartezeiten geändert
+    [log-channel] hat den Protokollkanal geändert
+    [mute] hat stummgeschaltet:
+    [unmute] hat wieder laut geschaltet:
+    [clear] hat den Willkommenssound entfernt von
+    [approve] hat den Willkommenssound abgesegnet von
+    [reject] hat den Willkommenssound abgelehnt von
+    [language] hat die Sprache geändert
+    [privacy-delete] hat die eigenen Daten gelöscht
+   *[other] { $action }
+}
+audit-entry-change = { $time } { $actor } { audit-action }: { $old } → { $new }
+audit-entry-user = { $time } { $actor } { audit-action } { $target }: { $old } → { $new }
+audit-empty = Hier wurde noch nichts geändert. 📭
+locale-name = Deutsch
+language-set = Die Sprache ist jetzt { $language }. 🗣️
+language-reset = Die Sprache wird jetzt wieder automatisch gewählt. 🗣️
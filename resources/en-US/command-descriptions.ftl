@@ -7,4 +7,27 @@ base-set-url = Set your chime from an URL
 base-set-url-link = Downloads a file as your chime
 base-admin = Bot configuration
 base-admin-forbid = Set a role whose users chimes are not played
-base-admin-forbid-role = The single role to blacklist
\ No newline at end of file
+base-admin-forbid-role = The single role to blacklist
+base-privacy = Manage the data stored about you
+base-privacy-export = Sends you all data stored about you
+base-privacy-delete = Deletes all data stored about you
+base-admin-moderation = Play new chimes only after approval
+base-admin-moderation-channel = Channel where chimes are approved, leave empty to disable
+base-admin-mute = Stop playing a user's chime here
+base-admin-mute-user = The user to mute
+base-admin-unmute = Play a user's chime here again
+base-admin-unmute-user = The user to unmute
+base-admin-clear = Remove a user's current chime here
+base-admin-clear-user = The user whose chime to remove
+base-admin-cooldown = Limit how often chimes are played
+base-admin-cooldown-user = Seconds until a user's chime plays again, 0 for no limit, empty for default
+base-admin-cooldown-guild = Maximum chimes per minute, 0 for no limit, empty for default
+base-admin-log-channel = Set a channel where changes are logged
+base-admin-log-channel-channel = Channel for the log, leave empty to disable
+base-admin-audit = Show the latest changes
+base-admin-audit-limit = Number of entries, 10 if empty
+base-language = Set your language for responses
+base-language-locale = Language, leave empty to choose automatically
+base-admin-language = Set the default language of this server
+base-admin-language-locale = Language, leave empty to choose automatically
+message-use-as-chime = Use as my chime
@@ -1,10 +1,72 @@
 success = Success! 🎉
-fail = That did not work... 😟
-file-too-large = Thats too big for me! 🥵
+command-outdated = This command is outdated, please try again in a moment! 🔄
+file-too-large = { $size ->
+    [unknown] Thats too big for me, { $limit } KB at most please! 🥵
+   *[other] Thats too big for me, { $size } KB is more than { $limit } KB! 🥵
+}
 download-failed = Download failed... 😟
 bad-url = That URL does not work for me. 🤔
-duration-exceeded = Thats too long for me! 😳
+duration-exceeded = Thats too long for me, { $duration } seconds is more than { $limit } seconds! 😳
 data-unreadable = I can't read this data. 🧐
 internal-error = Whoops! An internal error occurred... 🤕
 only-in-guilds = This only works in guilds! ☝
 missing-permissions = Who do you think you are? 👿
+privacy-export-message = Here is all data I have stored about you. 📦
+privacy-export-sent = Check your direct messages! 📬
+privacy-export-dm-failed = I can't send you direct messages... 📪
+privacy-delete-confirm = Do you really want me to delete all data I have stored about you? This can not be undone! ⚠
+privacy-delete-button = Delete my data
+privacy-delete-done = All your data has been deleted. 🗑
+url-forbidden = I am not allowed to download from there! 🚫
+resolve-failed = I could not get any audio out of that link. 🙉
+no-audio-stream = There is no audio in there! 🔇
+unsupported-codec = I can't play { $codec } audio, only { $allowed }. 🤷
+unsupported-channels = { $channels } channels are too many for me, { $max } at most please. 🎧
+unsupported-sample-rate = A sample rate of { $rate } Hz is not supported, it must be between { $min } and { $max } Hz. 📉
+video-not-allowed = I only take audio, no videos! 🎬
+chime-silent = I can't hear anything in there! 🤫
+chime-pending-approval = Saved! Your chime will be played here once the moderators approved it. ⏳
+chime-still-pending = Saved! This chime is still waiting for the approval of the moderators here. ⏳
+chime-rejected = Saved, but the moderators rejected this chime here before, so it will not be played. 🚫
+moderation-enabled = New chimes need to be approved in { $channel } from now on. 🛂
+moderation-disabled = Chimes do not need to be approved anymore. 🛃
+moderation-review = { $user } has a new chime. May it be played?
+moderation-approve = Approve
+moderation-reject = Reject
+moderation-approved = Chime was approved by { $moderator }. ✅
+moderation-rejected = Chime was rejected by { $moderator }. ❌
+moderation-already-decided = This has been decided already. 🤷
+role-forbidden = Chimes of users with the role { $role } will not be played here anymore. 🚷
+cooldowns-set = { $user ->
+    [0] Chimes of a user can be played any time
+   *[other] Chimes of a user are played once every { $user } seconds at most
+}, { $guild ->
+    [0] with no limit for this server. ⏱
+   *[other] and { $guild } chimes per minute at most in this server. ⏱
+}
+user-muted = The chime of { $user } will not be played here anymore. 🔇
+user-unmuted = The chime of { $user } will be played here again. 🔊
+user-chime-cleared = The current chime of { $user } will not be played here anymore. 🗑
+user-has-no-chime = { $user } has no chime. 🤷
+rate-limited = Not so fast! Try again { $retry }. ⏱
+no-audio-attachment = That message has no audio file attached. 🔇
+audit-action = { $action ->
+    [forbid] changed the blocked role
+    [moderation] changed the moderation channel
+    [cooldown] changed the cooldowns
+    [log-channel] changed the log channel
+    [mute] muted
+    [unmute] unmuted
+    [clear] cleared the chime of
+    [approve] approved the chime of
+    [reject] rejected the chime of
+    [language] changed the language
+    [privacy-delete] deleted their data
+   *[other] { $action }
+}
+audit-entry-change = { $time } { $actor } { audit-action }: { $old } → { $new }
+audit-entry-user = { $time } { $actor } { audit-action } { $target }: { $old } → { $new }
+audit-empty = Nothing has been changed here yet. 📭
+locale-name = English
+language-set = The language is now { $language }. 🗣️
+language-reset = The language is chosen automatically again. 🗣️
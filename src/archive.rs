@@ -0,0 +1,459 @@
+use crate::{chimes::ChimeSink, data, tempfiles::TempDir};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs::{self, File},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+const MANIFEST_NAME: &str = "manifest.json";
+const MANIFEST_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChimeEntry {
+    user_id: u64,
+    /// path of chime inside archive
+    file: String,
+    size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    created: String,
+    chimes: Vec<ChimeEntry>,
+    guilds: Vec<data::GuildDetails>,
+    // added in version 2
+    #[serde(default)]
+    reviews: Vec<data::ChimeReview>,
+    #[serde(default)]
+    guild_users: Vec<data::GuildUserDetails>,
+    #[serde(default)]
+    audit_entries: Vec<data::AuditEntry>,
+    #[serde(default)]
+    users: Vec<data::UserDetails>,
+    #[serde(default)]
+    recent_urls: Vec<data::RecentUrl>,
+}
+impl Manifest {
+    fn counts(&self) -> [(&'static str, usize); 7] {
+        [
+            ("chimes", self.chimes.len()),
+            ("guilds", self.guilds.len()),
+            ("reviews", self.reviews.len()),
+            ("guild users", self.guild_users.len()),
+            ("audit entries", self.audit_entries.len()),
+            ("users", self.users.len()),
+            ("recent urls", self.recent_urls.len()),
+        ]
+    }
+}
+
+/// Lists non-zero counts, e.g. "2 chimes, 1 guilds"
+fn describe_counts(counts: &[(&str, usize)]) -> String {
+    let described: Vec<String> = counts
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(name, count)| format!("{count} {name}"))
+        .collect();
+    if described.is_empty() {
+        "nothing".to_string()
+    } else {
+        described.join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// keep existing data
+    Skip,
+    /// replace existing data
+    Overwrite,
+    /// import nothing if any data exists already
+    Abort,
+}
+impl FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(ConflictStrategy::Skip),
+            "overwrite" => Ok(ConflictStrategy::Overwrite),
+            "abort" => Ok(ConflictStrategy::Abort),
+            other => Err(format!("Unknown conflict strategy '{other}'")),
+        }
+    }
+}
+
+/// Exports all chimes and data to path, staging chimes in staging
+pub async fn export(
+    sink: &dyn ChimeSink,
+    database: &data::DatabaseInterface,
+    path: &Path,
+    staging: &TempDir,
+) -> Result<(), Box<dyn Error>> {
+    let mut chimes = vec![];
+    for user_id in sink.get_users().await {
+        let staged = staging.path().join(user_id.to_string());
+
+        if let Err(why) = sink.export_data(user_id, staged.clone()).await {
+            // chime may have been cleared after listing
+            warn!("Could not export chime of user {user_id}: {why:?}");
+            continue;
+        }
+
+        chimes.push(ChimeEntry {
+            user_id,
+            file: format!("chimes/{user_id}"),
+            size: fs::metadata(&staged)?.len(),
+        });
+    }
+
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        created: chrono::Utc::now().to_rfc3339(),
+        chimes,
+        guilds: database.get_all_guild_details().await?,
+        reviews: database.get_all_reviews().await?,
+        guild_users: database.get_all_guild_user_details().await?,
+        audit_entries: database.get_all_audit_entries().await?,
+        users: database.get_all_user_details().await?,
+        recent_urls: database.get_all_recent_urls().await?,
+    };
+    let manifest_data = serde_json::to_vec_pretty(&manifest)?;
+
+    let mut builder =
+        tar::Builder::new(GzEncoder::new(File::create(path)?, Compression::default()));
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    builder.append_data(&mut header, MANIFEST_NAME, manifest_data.as_slice())?;
+
+    for entry in &manifest.chimes {
+        builder
+            .append_path_with_name(staging.path().join(entry.user_id.to_string()), &entry.file)?;
+    }
+
+    builder.into_inner()?.finish()?;
+
+    info!(
+        "Exported {} to {}",
+        describe_counts(&manifest.counts()),
+        path.display()
+    );
+
+    Ok(())
+}
+
+fn staged_path(staging: &TempDir, file: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let relative = Path::new(file);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("Invalid path in manifest: '{file}'").into());
+    }
+    Ok(staging.path().join(relative))
+}
+
+/// Unpacks archive at path to staging, where it must contain regular files only
+fn unpack(path: &Path, staging: &TempDir) -> Result<(), Box<dyn Error>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        // links could make chimes of files outside the archive
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            return Err(format!(
+                "Archive contains '{}', which is not a regular file",
+                entry.path()?.display()
+            )
+            .into());
+        }
+        entry.unpack_in(staging.path())?;
+    }
+    Ok(())
+}
+
+/// Imports archive at path, unpacking it to staging
+pub async fn import(
+    sink: &dyn ChimeSink,
+    database: &data::DatabaseInterface,
+    path: &Path,
+    strategy: ConflictStrategy,
+    staging: &TempDir,
+) -> Result<(), Box<dyn Error>> {
+    unpack(path, staging)?;
+
+    let manifest: Manifest =
+        serde_json::from_slice(&fs::read(staging.path().join(MANIFEST_NAME))?)?;
+    // older versions lack some data, which is left empty
+    if manifest.version == 0 || manifest.version > MANIFEST_VERSION {
+        return Err(format!("Unsupported archive version {}", manifest.version).into());
+    }
+
+    // verify archive before touching any data
+    for entry in &manifest.chimes {
+        let metadata = fs::symlink_metadata(staged_path(staging, &entry.file)?)
+            .map_err(|why| format!("Chime of user {} is missing: {why}", entry.user_id))?;
+        if !metadata.is_file() {
+            return Err(format!("Chime of user {} is not a regular file", entry.user_id).into());
+        }
+        let size = metadata.len();
+        if size != entry.size {
+            return Err(format!(
+                "Chime of user {} has size {size}, expected {}",
+                entry.user_id, entry.size
+            )
+            .into());
+        }
+    }
+
+    let existing_chimes: HashSet<u64> = sink.get_users().await.into_iter().collect();
+    let existing_guilds: HashSet<u64> = database
+        .get_all_guild_details()
+        .await?
+        .into_iter()
+        .map(|details| details.id)
+        .collect();
+    let existing_reviews = database.get_all_reviews().await?;
+    let existing_review_ids: HashSet<i64> =
+        existing_reviews.iter().map(|review| review.id).collect();
+    let existing_reviewed_chimes: HashSet<(u64, u64, String)> = existing_reviews
+        .into_iter()
+        .map(|review| (review.guild_id, review.user_id, review.chime_id))
+        .collect();
+    let existing_guild_users: HashSet<(u64, u64)> = database
+        .get_all_guild_user_details()
+        .await?
+        .into_iter()
+        .map(|details| (details.guild_id, details.user_id))
+        .collect();
+    let existing_audit_entries: HashSet<i64> = database
+        .get_all_audit_entries()
+        .await?
+        .into_iter()
+        .map(|entry| entry.id)
+        .collect();
+    let existing_users: HashSet<u64> = database
+        .get_all_user_details()
+        .await?
+        .into_iter()
+        .map(|details| details.id)
+        .collect();
+    let existing_recent_urls: HashSet<(u64, String)> = database
+        .get_all_recent_urls()
+        .await?
+        .into_iter()
+        .map(|recent| (recent.user_id, recent.url))
+        .collect();
+
+    let chime_exists = |entry: &ChimeEntry| existing_chimes.contains(&entry.user_id);
+    let guild_exists = |details: &data::GuildDetails| existing_guilds.contains(&details.id);
+    // reviews are referenced by id from moderation posts, so their ids are kept as well
+    let review_exists = |review: &data::ChimeReview| {
+        existing_review_ids.contains(&review.id)
+            || existing_reviewed_chimes.contains(&(
+                review.guild_id,
+                review.user_id,
+                review.chime_id.clone(),
+            ))
+    };
+    let guild_user_exists = |details: &data::GuildUserDetails| {
+        existing_guild_users.contains(&(details.guild_id, details.user_id))
+    };
+    let audit_entry_exists = |entry: &data::AuditEntry| existing_audit_entries.contains(&entry.id);
+    let user_exists = |details: &data::UserDetails| existing_users.contains(&details.id);
+    let recent_url_exists = |recent: &data::RecentUrl| {
+        existing_recent_urls.contains(&(recent.user_id, recent.url.clone()))
+    };
+
+    if strategy == ConflictStrategy::Abort {
+        let conflicts = [
+            (
+                "chimes",
+                manifest.chimes.iter().filter(|e| chime_exists(e)).count(),
+            ),
+            (
+                "guilds",
+                manifest.guilds.iter().filter(|d| guild_exists(d)).count(),
+            ),
+            (
+                "reviews",
+                manifest.reviews.iter().filter(|r| review_exists(r)).count(),
+            ),
+            (
+                "guild users",
+                manifest
+                    .guild_users
+                    .iter()
+                    .filter(|d| guild_user_exists(d))
+                    .count(),
+            ),
+            (
+                "audit entries",
+                manifest
+                    .audit_entries
+                    .iter()
+                    .filter(|e| audit_entry_exists(e))
+                    .count(),
+            ),
+            (
+                "users",
+                manifest.users.iter().filter(|d| user_exists(d)).count(),
+            ),
+            (
+                "recent urls",
+                manifest
+                    .recent_urls
+                    .iter()
+                    .filter(|r| recent_url_exists(r))
+                    .count(),
+            ),
+        ];
+        if conflicts.iter().any(|(_, count)| *count > 0) {
+            return Err(format!(
+                "Aborting import, {} exist already",
+                describe_counts(&conflicts)
+            )
+            .into());
+        }
+    }
+    let skip = strategy == ConflictStrategy::Skip;
+
+    let mut imported_chimes = 0;
+    for entry in &manifest.chimes {
+        if skip && chime_exists(entry) {
+            continue;
+        }
+        sink.save_data(entry.user_id, staged_path(staging, &entry.file)?)
+            .await
+            .map_err(|why| format!("Could not save chime of user {}: {why:?}", entry.user_id))?;
+        imported_chimes += 1;
+    }
+
+    let mut imported_guilds = 0;
+    for details in &manifest.guilds {
+        if skip && guild_exists(details) {
+            continue;
+        }
+        database.set_guild_details(details.clone()).await?;
+        imported_guilds += 1;
+    }
+
+    let mut imported_reviews = 0;
+    for review in &manifest.reviews {
+        if skip && review_exists(review) {
+            continue;
+        }
+        database.import_review(review).await?;
+        imported_reviews += 1;
+    }
+
+    let mut imported_guild_users = 0;
+    for details in &manifest.guild_users {
+        if skip && guild_user_exists(details) {
+            continue;
+        }
+        database.set_guild_user_details(details.clone()).await?;
+        imported_guild_users += 1;
+    }
+
+    let mut imported_audit_entries = 0;
+    for entry in &manifest.audit_entries {
+        if skip && audit_entry_exists(entry) {
+            continue;
+        }
+        database.import_audit_entry(entry).await?;
+        imported_audit_entries += 1;
+    }
+
+    let mut imported_users = 0;
+    for details in &manifest.users {
+        if skip && user_exists(details) {
+            continue;
+        }
+        database.set_user_details(details.clone()).await?;
+        imported_users += 1;
+    }
+
+    let mut imported_recent_urls = 0;
+    for recent in &manifest.recent_urls {
+        if skip && recent_url_exists(recent) {
+            continue;
+        }
+        database.set_recent_url(recent).await?;
+        imported_recent_urls += 1;
+    }
+
+    database.reset_id_sequences().await?;
+
+    info!(
+        "Imported {} from {}",
+        describe_counts(&[
+            ("chimes", imported_chimes),
+            ("guilds", imported_guilds),
+            ("reviews", imported_reviews),
+            ("guild users", imported_guild_users),
+            ("audit entries", imported_audit_entries),
+            ("users", imported_users),
+            ("recent urls", imported_recent_urls),
+        ]),
+        path.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_manifest_of_version_1() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "version": 1,
+                "created": "2024-01-01T00:00:00+00:00",
+                "chimes": [{ "user_id": 3, "file": "chimes/3", "size": 2 }],
+                "guilds": [{ "id": 7, "blocked_role_id": null }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.chimes.len(), 1);
+        assert_eq!(manifest.guilds[0].id, 7);
+        assert_eq!(
+            describe_counts(&manifest.counts()),
+            "1 chimes, 1 guilds".to_string()
+        );
+    }
+
+    #[test]
+    fn reject_links_in_archive() {
+        let staging = TempDir::unique(&std::env::temp_dir().join("dab-rs-archive-test")).unwrap();
+        let path = staging.path().join("archive.tar.gz");
+
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "chimes/3", "/etc/passwd")
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let unpacked = TempDir::unique(&std::env::temp_dir().join("dab-rs-archive-test")).unwrap();
+        assert!(unpack(&path, &unpacked).is_err());
+        assert!(fs::symlink_metadata(unpacked.path().join("chimes/3")).is_err());
+    }
+}
@@ -2,8 +2,14 @@ use async_trait::async_trait;
 use fs_extra::file::CopyOptions;
 use log::{error, warn};
 use songbird::input::*;
-use std::collections::HashMap;
-use tokio::sync::Mutex;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tokio::{process::Command, sync::Mutex, task};
+
+const BLOB_DIR_NAME: &str = "blobs";
 
 #[derive(Debug)]
 pub enum ChimeSinkError {
@@ -11,29 +17,80 @@ pub enum ChimeSinkError {
     Playback,
     SaveError,
     DirError,
+    NotApproved,
 }
 
 #[async_trait]
 pub trait ChimeSink: Send + Sync {
     async fn has_data(&self, user_id: u64) -> bool;
-    async fn get_input(&self, user_id: u64) -> Result<Input, ChimeSinkError>;
-    async fn save_data(&self, user_id: u64, file: std::path::PathBuf)
-        -> Result<(), ChimeSinkError>;
+    /// Identifies the current chime of user, changes whenever it does
+    async fn get_chime_id(&self, user_id: u64) -> Option<String>;
+    async fn get_input(&self, user_id: u64, guild_id: u64) -> Result<Input, ChimeSinkError>;
+    async fn save_data(&self, user_id: u64, file: PathBuf) -> Result<(), ChimeSinkError>;
     async fn clear_data(&self, user_id: u64);
+    async fn export_data(&self, user_id: u64, dest: PathBuf) -> Result<(), ChimeSinkError>;
+    async fn get_users(&self) -> Vec<u64>;
+    async fn is_available(&self) -> bool;
+}
+
+/// Hash of the decoded audio, so that the same sound in different containers is stored once
+async fn audio_hash(file: &Path) -> Option<String> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(file)
+        .args([
+            "-vn",
+            "-ac",
+            "2",
+            "-ar",
+            "48000",
+            "-c:a",
+            "pcm_s16le",
+            "-f",
+            "hash",
+            "-hash",
+            "sha256",
+            "-",
+        ])
+        .kill_on_drop(true)
+        .output()
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .strip_prefix("SHA256=")
+            .map(str::to_owned),
+        Ok(output) => {
+            error!(
+                "Could not hash {}: {}",
+                file.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            None
+        }
+        Err(why) => {
+            error!("Could not run ffmpeg: {:#?}", why);
+            None
+        }
+    }
 }
 
+/// Stores each unique chime once in `blobs/`, users reference them by symlink
 pub struct FileChimeSink {
-    dir: std::path::PathBuf,
-    chimes: Mutex<HashMap<u64, std::path::PathBuf>>,
+    dir: PathBuf,
+    blob_dir: PathBuf,
+    /// content hash of chime per user
+    chimes: Mutex<HashMap<u64, String>>,
 }
 impl FileChimeSink {
-    pub async fn new(mut dir: std::path::PathBuf) -> Result<Self, ChimeSinkError> {
+    pub async fn new(mut dir: PathBuf) -> Result<Self, ChimeSinkError> {
         if dir.is_file() {
             return Err(ChimeSinkError::DirError);
         }
 
         if !dir.is_dir() {
-            if let Err(why) = std::fs::create_dir_all(&dir) {
+            if let Err(why) = fs::create_dir_all(&dir) {
                 error!("Could not ensure directory at {} : {}", dir.display(), why);
                 return Err(ChimeSinkError::DirError);
             }
@@ -49,13 +106,27 @@ impl FileChimeSink {
             }
         }
 
-        let paths = std::fs::read_dir(&dir);
+        let blob_dir = dir.join(BLOB_DIR_NAME);
+        if let Err(why) = fs::create_dir_all(&blob_dir) {
+            error!(
+                "Could not ensure directory at {} : {}",
+                blob_dir.display(),
+                why
+            );
+            return Err(ChimeSinkError::DirError);
+        }
+
+        let paths = fs::read_dir(&dir);
         if paths.is_err() {
             return Err(ChimeSinkError::DirError);
         }
 
-        let chimes = Mutex::new(HashMap::<u64, std::path::PathBuf>::new());
-        let mut chimes_locked = chimes.lock().await;
+        let sink = Self {
+            dir,
+            blob_dir,
+            chimes: Mutex::new(HashMap::new()),
+        };
+        let mut chimes = sink.chimes.lock().await;
 
         for path in paths.unwrap() {
             if path.is_err() {
@@ -65,28 +136,171 @@ impl FileChimeSink {
 
             let path = path.unwrap().path();
 
-            if !path.is_file() {
+            if path.is_dir() {
                 continue;
             }
 
-            if let Some(prefix) = path.file_stem() {
-                let user_id = prefix.to_str().and_then(|s| s.parse::<u64>().ok());
-                if user_id.is_none() {
-                    warn!("Invalid file in directory: {:#?}", prefix);
-                    continue;
+            let user_id = path
+                .file_stem()
+                .and_then(|prefix| prefix.to_str())
+                .and_then(|s| s.parse::<u64>().ok());
+            if user_id.is_none() {
+                warn!("Invalid file in directory: {:#?}", path);
+                continue;
+            }
+            let user_id = user_id.unwrap();
+
+            let hash = match fs::read_link(&path) {
+                Ok(target) => {
+                    if !path.is_file() {
+                        warn!("Dangling chime of user {user_id}: {:#?}", target);
+                        continue;
+                    }
+                    target
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map(str::to_owned)
                 }
+                // regular file from before deduplication
+                Err(_) => sink.migrate(user_id, &path).await,
+            };
 
-                chimes_locked.insert(user_id.unwrap(), path);
+            match hash {
+                Some(hash) => _ = chimes.insert(user_id, hash),
+                None => warn!("Could not load chime {:#?}", path),
             }
         }
 
-        if chimes_locked.len() == 0 {
+        if chimes.is_empty() {
             warn!("No chimes found");
         }
 
-        drop(chimes_locked);
+        // remove blobs that lost their references while offline
+        if let Ok(blobs) = fs::read_dir(&sink.blob_dir) {
+            for blob in blobs.flatten() {
+                let hash = blob.file_name().to_string_lossy().into_owned();
+                sink.release_blob(&chimes, &hash);
+            }
+        }
+
+        drop(chimes);
+
+        Ok(sink)
+    }
+
+    fn user_path(&self, user_id: u64) -> PathBuf {
+        self.dir.join(user_id.to_string())
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.blob_dir.join(hash)
+    }
+
+    /// Moves chime of user from before deduplication into blob storage, returns its hash
+    async fn migrate(&self, user_id: u64, path: &Path) -> Option<String> {
+        let hash = audio_hash(path).await?;
+
+        if !self.store_blob(path, &hash).await {
+            return None;
+        }
+
+        if let Err(why) = self.link_user(user_id, &hash) {
+            error!("Could not link chime of user {user_id}: {why}");
+            return None;
+        }
+
+        Some(hash)
+    }
+
+    /// Moves file into blob storage, unless its content is stored already
+    async fn store_blob(&self, file: &Path, hash: &str) -> bool {
+        let blob_dir = self.blob_dir.clone();
+        let blob_path = self.blob_path(hash);
+        let file = file.to_path_buf();
+
+        // moving may copy across file systems
+        task::spawn_blocking(move || Self::move_to_blob(&blob_dir, &file, &blob_path))
+            .await
+            .unwrap_or_else(|why| {
+                error!("Could not store blob {hash}: {why:?}");
+                false
+            })
+    }
+
+    fn move_to_blob(blob_dir: &Path, file: &Path, blob_path: &Path) -> bool {
+        if blob_path.is_file() {
+            if let Err(why) = fs::remove_file(file) {
+                warn!("Could not remove duplicate {}: {}", file.display(), why);
+            }
+            return true;
+        }
+
+        if let Err(why) = fs_extra::file::move_file(file, blob_path, &CopyOptions::default()) {
+            error!(
+                "Could not move file {} to {}: {}",
+                file.display(),
+                blob_path.display(),
+                why
+            );
+            return false;
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            match fs::metadata(blob_dir) {
+                Ok(metadata) => {
+                    // remove x flag while respecting parent dir
+                    let mode = metadata.permissions().mode() & !0o111;
+                    if let Err(why) =
+                        fs::set_permissions(blob_path, fs::Permissions::from_mode(mode))
+                    {
+                        warn!(
+                            "Could not set permissions of file {} to {}: {}",
+                            blob_path.display(),
+                            mode,
+                            why
+                        );
+                    }
+                }
+                Err(why) => warn!(
+                    "Could not retrieve metadata for path {}: {}",
+                    blob_dir.display(),
+                    why
+                ),
+            }
+        }
+
+        true
+    }
+
+    fn link_user(&self, user_id: u64, hash: &str) -> std::io::Result<()> {
+        let user_path = self.user_path(user_id);
+        if let Err(why) = fs::remove_file(&user_path) {
+            if why.kind() != std::io::ErrorKind::NotFound {
+                return Err(why);
+            }
+        }
+        std::os::unix::fs::symlink(Path::new(BLOB_DIR_NAME).join(hash), user_path)
+    }
+
+    /// Removes file handed to the sink that could not be stored
+    fn discard(file: &Path) {
+        if let Err(why) = fs::remove_file(file) {
+            if why.kind() != std::io::ErrorKind::NotFound {
+                warn!("Could not remove {}: {}", file.display(), why);
+            }
+        }
+    }
 
-        Ok(Self { dir, chimes })
+    /// Removes blob if no user references it anymore
+    fn release_blob(&self, chimes: &HashMap<u64, String>, hash: &str) {
+        if chimes.values().any(|h| h == hash) {
+            return;
+        }
+        if let Err(why) = fs::remove_file(self.blob_path(hash)) {
+            error!("Could not remove unreferenced chime {hash}: {:#?}", why);
+        }
     }
 }
 #[async_trait]
@@ -95,9 +309,14 @@ impl ChimeSink for FileChimeSink {
         self.chimes.lock().await.contains_key(&user_id)
     }
 
-    async fn get_input(&self, user_id: u64) -> Result<Input, ChimeSinkError> {
+    async fn get_chime_id(&self, user_id: u64) -> Option<String> {
+        self.chimes.lock().await.get(&user_id).cloned()
+    }
+
+    /// Plays the same chime in every guild
+    async fn get_input(&self, user_id: u64, _guild_id: u64) -> Result<Input, ChimeSinkError> {
         match self.chimes.lock().await.get(&user_id) {
-            Some(input) => match ffmpeg(input).await {
+            Some(hash) => match ffmpeg(self.blob_path(hash)).await {
                 Ok(inp) => Ok(inp),
                 Err(why) => {
                     error!("Could not playback chime {:#?}", why);
@@ -108,69 +327,73 @@ impl ChimeSink for FileChimeSink {
         }
     }
 
-    async fn save_data(
-        &self,
-        user_id: u64,
-        file: std::path::PathBuf,
-    ) -> Result<(), ChimeSinkError> {
-        let mut new_path = self.dir.clone();
-        new_path.push(format!("{user_id}"));
-
-        if self.has_data(user_id).await {
-            self.clear_data(user_id).await;
-        }
-
-        match fs_extra::file::move_file(&file, &new_path, &CopyOptions::default()) {
-            Ok(_) => {
-                #[cfg(unix)]
-                {
-                    use std::{fs, os::unix::fs::PermissionsExt};
-                    if let Some(parent) = new_path.parent() {
-                        match fs::metadata(parent) {
-                            Ok(metadata) => {
-                                // remove x flag while respecting parent dir
-                                let mode = metadata.permissions().mode() & !0o111;
-                                if let Err(why) =
-                                    fs::set_permissions(&new_path, fs::Permissions::from_mode(mode))
-                                {
-                                    warn!(
-                                        "Could not set permissions of file {} to {}: {}",
-                                        new_path.display(),
-                                        mode,
-                                        why
-                                    );
-                                }
-                            }
-                            Err(why) => warn!(
-                                "Could not retrieve metadata for path {}: {}",
-                                parent.display(),
-                                why
-                            ),
-                        }
-                    } else {
-                        warn!("File {} has no parent!", new_path.display())
-                    }
-                }
-                self.chimes.lock().await.insert(user_id, new_path);
-                Ok(())
-            }
-            Err(why) => {
-                error!(
-                    "Could not move file {} to {}: {}",
-                    file.display(),
-                    new_path.display(),
-                    why
-                );
-                Err(ChimeSinkError::SaveError)
+    async fn save_data(&self, user_id: u64, file: PathBuf) -> Result<(), ChimeSinkError> {
+        let hash = match audio_hash(&file).await {
+            Some(hash) => hash,
+            None => {
+                Self::discard(&file);
+                return Err(ChimeSinkError::SaveError);
             }
+        };
+
+        let mut chimes = self.chimes.lock().await;
+
+        if !self.store_blob(&file, &hash).await {
+            Self::discard(&file);
+            return Err(ChimeSinkError::SaveError);
+        }
+
+        if let Err(why) = self.link_user(user_id, &hash) {
+            error!("Could not link chime of user {user_id}: {why}");
+            self.release_blob(&chimes, &hash);
+            return Err(ChimeSinkError::SaveError);
+        }
+
+        if let Some(previous) = chimes.insert(user_id, hash) {
+            self.release_blob(&chimes, &previous);
         }
+
+        Ok(())
     }
 
     async fn clear_data(&self, user_id: u64) {
-        if let Some(path) = self.chimes.lock().await.remove(&user_id) {
-            if let Err(why) = std::fs::remove_file(path) {
+        let mut chimes = self.chimes.lock().await;
+
+        if let Some(hash) = chimes.remove(&user_id) {
+            if let Err(why) = fs::remove_file(self.user_path(user_id)) {
                 error!("Could not remove entry for user: {:#?}", why);
             }
+            self.release_blob(&chimes, &hash);
         }
     }
+
+    async fn export_data(&self, user_id: u64, dest: PathBuf) -> Result<(), ChimeSinkError> {
+        match self.chimes.lock().await.get(&user_id) {
+            Some(hash) => {
+                let path = self.blob_path(hash);
+                fs::copy(&path, &dest).map(|_| ()).map_err(|why| {
+                    error!(
+                        "Could not copy file {} to {}: {}",
+                        path.display(),
+                        dest.display(),
+                        why
+                    );
+                    ChimeSinkError::SaveError
+                })
+            }
+            None => Err(ChimeSinkError::DataNotAvailable),
+        }
+    }
+
+    async fn get_users(&self) -> Vec<u64> {
+        let mut users: Vec<u64> = self.chimes.lock().await.keys().copied().collect();
+        users.sort_unstable();
+        users
+    }
+
+    async fn is_available(&self) -> bool {
+        fs::metadata(&self.blob_dir)
+            .map(|metadata| metadata.is_dir() && !metadata.permissions().readonly())
+            .unwrap_or(false)
+    }
 }
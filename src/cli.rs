@@ -0,0 +1,176 @@
+use crate::{chimes::ChimeSink, *};
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: dab-rs [options] [COMMAND]
+
+Without COMMAND, the bot connects to discord. Commands run offline, except for
+'commands clear':
+    chime list                          List users with a chime
+    chime import USER_ID FILE           Validate FILE and set it as chime of user
+    chime export USER_ID FILE           Copy chime of user to FILE
+    chime delete USER_ID                Clear chime of user
+    guild show GUILD_ID                 Show settings of guild
+    guild set GUILD_ID SETTING VALUE    Set 'blocked-role', 'moderation-channel',
+                                        'audit-channel', 'user-cooldown' (seconds)
+                                        or 'guild-plays' (per minute) of guild,
+                                        'none' to unset
+    validate FILE                       Check FILE against the rules for chimes
+    archive export FILE                 Export all chimes and data to FILE
+    archive import FILE [STRATEGY]      Import archive FILE, on existing data either
+                                        'skip' (default), 'overwrite' or 'abort'
+    commands clear                      Remove commands registered outside of
+                                        COMMAND_GUILDS, or in guilds if unset";
+
+/// Directory of this run, so runs in parallel and the bot keep their files
+fn temp_dir(settings: &HashMap<String, String>) -> Result<tempfiles::TempDir, String> {
+    tempfiles::TempDir::unique(&temp_root(settings).join("cli"))
+        .map_err(|why| format!("Could not prepare temporary directory: {why}"))
+}
+
+fn parse_id(id: &str) -> Result<u64, String> {
+    id.parse::<u64>()
+        .map_err(|why| format!("Invalid id '{id}': {why}"))
+}
+
+async fn validate(file: &str, settings: &HashMap<String, String>) -> Result<(), String> {
+    validation::validate_chime(&PathBuf::from(file), &validation_policy(settings))
+        .await
+        .map_err(|why| format!("File '{file}' is not a valid chime: {why}"))
+}
+
+pub async fn run(args: &[String], settings: &HashMap<String, String>) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["chime", "list"] => {
+            for user_id in create_sink(settings).await.get_users().await {
+                println!("{user_id}");
+            }
+        }
+        ["chime", "import", user_id, file] => {
+            let user_id = parse_id(user_id)?;
+
+            // sink takes ownership of the copy, which is removed if it is left over
+            let temp_dir = temp_dir(settings)?;
+            let temp_file = temp_dir.file();
+            std::fs::copy(file, temp_file.path())
+                .map_err(|why| format!("Could not copy '{file}': {why}"))?;
+
+            validation::prepare_chime(temp_file.path(), &validation_policy(settings))
+                .await
+                .map_err(|why| format!("File '{file}' is not a valid chime: {why}"))?;
+
+            create_sink(settings)
+                .await
+                .save_data(user_id, temp_file.path().to_path_buf())
+                .await
+                .map_err(|why| format!("Could not save chime: {why:?}"))?;
+            println!("Imported chime of user {user_id}");
+        }
+        ["chime", "export", user_id, file] => {
+            let user_id = parse_id(user_id)?;
+            create_sink(settings)
+                .await
+                .export_data(user_id, PathBuf::from(file))
+                .await
+                .map_err(|why| format!("Could not export chime of user {user_id}: {why:?}"))?;
+            println!("Exported chime of user {user_id} to '{file}'");
+        }
+        ["chime", "delete", user_id] => {
+            let user_id = parse_id(user_id)?;
+            let sink = create_sink(settings).await;
+            if !sink.has_data(user_id).await {
+                return Err(format!("User {user_id} has no chime"));
+            }
+            sink.clear_data(user_id).await;
+            println!("Deleted chime of user {user_id}");
+        }
+        ["guild", "show", guild_id] => {
+            let guild_id = parse_id(guild_id)?;
+            match connect_database(settings)
+                .await
+                .get_guild_details(&guild_id)
+                .await
+            {
+                Some(details) => println!("{details:#?}"),
+                None => println!("No details for guild {guild_id}"),
+            }
+        }
+        ["guild", "set", guild_id, setting @ ("blocked-role" | "moderation-channel" | "audit-channel" | "user-cooldown"
+        | "guild-plays"), value] => {
+            let guild_id = parse_id(guild_id)?;
+            let value = match *value {
+                "none" => None,
+                value => Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|why| format!("Invalid value '{value}': {why}"))?,
+                ),
+            };
+
+            let database = connect_database(settings).await;
+            match *setting {
+                "blocked-role" => database.set_blocked_role_id(guild_id, value).await,
+                "moderation-channel" => database.set_moderation_channel_id(guild_id, value).await,
+                "audit-channel" => database.set_audit_channel_id(guild_id, value).await,
+                "user-cooldown" => database.set_user_cooldown_seconds(guild_id, value).await,
+                _ => database.set_guild_plays_per_minute(guild_id, value).await,
+            }
+            .map_err(|why| format!("Could not set details of guild {guild_id}: {why}"))?;
+            println!("Updated guild {guild_id}");
+        }
+        ["validate", file] => {
+            validate(file, settings).await?;
+            println!("File '{file}' is a valid chime");
+        }
+        ["archive", "export", file] => {
+            archive::export(
+                &create_sink(settings).await,
+                &connect_database(settings).await,
+                &PathBuf::from(file),
+                &temp_dir(settings)?,
+            )
+            .await
+            .map_err(|why| format!("Could not export archive: {why}"))?;
+            println!("Exported archive to '{file}'");
+        }
+        ["archive", "import", file, strategy @ ..] if strategy.len() <= 1 => {
+            let strategy = strategy
+                .first()
+                .map_or(Ok(archive::ConflictStrategy::Skip), |s| s.parse())?;
+
+            archive::import(
+                &create_sink(settings).await,
+                &connect_database(settings).await,
+                &PathBuf::from(file),
+                strategy,
+                &temp_dir(settings)?,
+            )
+            .await
+            .map_err(|why| format!("Could not import archive: {why}"))?;
+            println!("Imported archive '{file}'");
+        }
+        ["commands", "clear"] => {
+            let http = serenity::http::Http::new(&settings["API_TOKEN"]);
+            let application = http
+                .get_current_application_info()
+                .await
+                .map_err(|why| format!("Could not get application: {why}"))?;
+            http.set_application_id(application.id.0);
+
+            let cleared = registration::clear_stale(&http, &command_scope(settings))
+                .await
+                .map_err(|why| format!("Could not clear commands: {why}"))?;
+            if cleared.is_empty() {
+                println!("No stale commands");
+            }
+            for target in cleared {
+                println!("Cleared commands in {target}");
+            }
+        }
+        _ => return Err(format!("Unknown command '{}'", args.join(" "))),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,404 @@
+use crate::{
+    error::CommandError, fluent::FluentLocalizer, localizable::Localizable, nameable::Nameable,
+    response::Response,
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption},
+    json,
+    model::{
+        application::{
+            command::{CommandOptionType, CommandType},
+            interaction::{
+                application_command::{
+                    ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+                    ResolvedTarget,
+                },
+                autocomplete::AutocompleteInteraction,
+            },
+        },
+        channel::Message,
+    },
+    prelude::Context,
+};
+
+/// Localizes descriptions of registered commands into every available locale
+pub struct Localization<'a> {
+    pub localizer: &'a FluentLocalizer,
+    pub locales: Vec<String>,
+}
+impl<'a> Localization<'a> {
+    pub fn new(localizer: &'a FluentLocalizer) -> Self {
+        Localization {
+            localizer,
+            locales: localizer.get_available_localizations(),
+        }
+    }
+
+    /// Describes cmd by msg in all locales and names it, by default after the last part of msg.
+    /// Other locales may translate the name by a `.name` attribute of msg, while interactions
+    /// keep using the default name
+    pub fn apply<'b, T>(&self, cmd: &'b mut T, msg: &str, name: Option<&str>) -> &'b mut T
+    where
+        T: Localizable + Nameable,
+    {
+        let default_locale = self.localizer.fallback_locale.to_string();
+
+        let mut cmd = cmd.localize_default(&self.localizer.localize(&default_locale, msg, None));
+
+        for loc in self.locales.iter().filter(|s| **s != default_locale) {
+            cmd = cmd.localize(loc.as_str(), &self.localizer.localize(loc, msg, None));
+            if let Some(name) = self.localizer.localize_attribute(loc, msg, "name") {
+                cmd = cmd.localize_name(loc.as_str(), &name);
+            }
+        }
+
+        cmd.name(name.unwrap_or_else(|| Self::default_name(msg)))
+    }
+
+    /// Names cmd by name, translated by msg in the other locales, for commands without
+    /// a description
+    pub fn apply_name<'b, T>(&self, cmd: &'b mut T, msg: &str, name: &str) -> &'b mut T
+    where
+        T: Localizable + Nameable,
+    {
+        let default_locale = self.localizer.fallback_locale.to_string();
+
+        let mut cmd = cmd.name(name);
+        for loc in self.locales.iter().filter(|s| **s != default_locale) {
+            cmd = cmd.localize_name(loc.as_str(), &self.localizer.localize(loc, msg, None));
+        }
+        cmd
+    }
+
+    fn default_name(msg: &str) -> &str {
+        msg.split('-').next_back().expect("Bad localizable name!")
+    }
+}
+
+/// Value of the first option of the subcommand invoked with option, if given
+pub fn first_value(option: &CommandDataOption) -> Option<&CommandDataOptionValue> {
+    option.options.first().and_then(|opt| opt.resolved.as_ref())
+}
+
+/// Text typed so far into the option of the subcommand that is being autocompleted
+pub fn focused_value(option: &CommandDataOption) -> Option<&str> {
+    option
+        .options
+        .iter()
+        .find(|opt| opt.focused)
+        .and_then(|opt| opt.value.as_ref())
+        .and_then(|value| value.as_str())
+}
+
+/// Error for options that do not match the registered subcommand
+pub fn malformed(option: &CommandDataOption) -> CommandError {
+    CommandError::Malformed(format!("{} {:?}", option.name, option.options))
+}
+
+/// Subcommand of the command root, declaring how it is registered and run
+#[async_trait]
+pub trait Subcommand<S: Sync>: Send + Sync {
+    /// Id of the localized description, e.g. `base-set-url`
+    fn description_id(&self) -> &'static str;
+
+    /// Name the subcommand is registered and dispatched by
+    fn name(&self) -> &'static str {
+        Localization::default_name(self.description_id())
+    }
+
+    fn kind(&self) -> CommandOptionType {
+        CommandOptionType::SubCommand
+    }
+
+    /// Adds the options of the subcommand
+    fn register(&self, _opt: &mut CreateApplicationCommandOption, _localization: &Localization) {}
+
+    /// Runs the subcommand invoked with option, returns the response to send unless it
+    /// responded itself
+    async fn run(
+        &self,
+        state: &S,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError>;
+
+    /// Suggests values for the focused option while option is being typed, only called for
+    /// options registered with autocomplete, which discord allows for text and numbers only
+    async fn autocomplete(
+        &self,
+        _state: &S,
+        _ctx: &Context,
+        _interaction: &AutocompleteInteraction,
+        _option: &CommandDataOption,
+    ) -> Result<Vec<String>, CommandError> {
+        Ok(vec![])
+    }
+}
+
+/// Command in the context menu of messages, run on the message it was picked for
+#[async_trait]
+pub trait MessageCommand<S: Sync>: Send + Sync {
+    /// Name the command is registered and dispatched by, e.g. `Use as my chime`
+    fn name(&self) -> &'static str;
+
+    /// Id of the localized name shown in the other locales
+    fn name_id(&self) -> &'static str;
+
+    /// Runs the command on message, returns the response to send unless it responded itself
+    async fn run(
+        &self,
+        state: &S,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        message: &Message,
+    ) -> Result<Option<Response>, CommandError>;
+}
+
+fn create_option<S: Sync>(
+    subcommand: &dyn Subcommand<S>,
+    opt: &mut CreateApplicationCommandOption,
+    localization: &Localization,
+) {
+    localization
+        .apply(opt, subcommand.description_id(), Some(subcommand.name()))
+        .kind(subcommand.kind());
+    subcommand.register(opt, localization);
+}
+
+/// The one of subcommands that option names, along with that option, or why there is none
+fn find<'s, 'o, S: Sync>(
+    subcommands: &'s [Box<dyn Subcommand<S>>],
+    option: Option<&'o CommandDataOption>,
+) -> Result<(&'s dyn Subcommand<S>, &'o CommandDataOption), String> {
+    let option = option.ok_or("missing subcommand")?;
+
+    match subcommands
+        .iter()
+        .find(|subcommand| subcommand.name() == option.name)
+    {
+        Some(subcommand) => Ok((subcommand.as_ref(), option)),
+        None => Err(format!("unknown subcommand '{}'", option.name)),
+    }
+}
+
+/// Runs the one of subcommands that option names
+async fn dispatch<S: Sync>(
+    subcommands: &[Box<dyn Subcommand<S>>],
+    state: &S,
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    option: Option<&CommandDataOption>,
+) -> Result<Option<Response>, CommandError> {
+    let (subcommand, option) = find(subcommands, option).map_err(CommandError::Malformed)?;
+    subcommand.run(state, ctx, command, option).await
+}
+
+/// Subcommand that only groups further subcommands, e.g. `set` of `set url`
+pub struct Group<S> {
+    description_id: &'static str,
+    subcommands: Vec<Box<dyn Subcommand<S>>>,
+}
+impl<S: Sync> Group<S> {
+    pub fn new(description_id: &'static str) -> Self {
+        Group {
+            description_id,
+            subcommands: vec![],
+        }
+    }
+
+    pub fn subcommand<T>(mut self, subcommand: T) -> Self
+    where
+        T: Subcommand<S> + 'static,
+    {
+        self.subcommands.push(Box::new(subcommand));
+        self
+    }
+}
+#[async_trait]
+impl<S: Sync> Subcommand<S> for Group<S> {
+    fn description_id(&self) -> &'static str {
+        self.description_id
+    }
+
+    fn kind(&self) -> CommandOptionType {
+        CommandOptionType::SubCommandGroup
+    }
+
+    fn register(&self, opt: &mut CreateApplicationCommandOption, localization: &Localization) {
+        for subcommand in &self.subcommands {
+            opt.create_sub_option(|sub| {
+                create_option(subcommand.as_ref(), sub, localization);
+                sub
+            });
+        }
+    }
+
+    async fn run(
+        &self,
+        state: &S,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        dispatch(
+            &self.subcommands,
+            state,
+            ctx,
+            command,
+            option.options.first(),
+        )
+        .await
+    }
+
+    async fn autocomplete(
+        &self,
+        state: &S,
+        ctx: &Context,
+        interaction: &AutocompleteInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Vec<String>, CommandError> {
+        let (subcommand, option) =
+            find(&self.subcommands, option.options.first()).map_err(CommandError::Malformed)?;
+        subcommand
+            .autocomplete(state, ctx, interaction, option)
+            .await
+    }
+}
+
+/// Root command along with all of its subcommands, and the commands in context menus
+pub struct CommandRegistry<S> {
+    root: String,
+    subcommands: Vec<Box<dyn Subcommand<S>>>,
+    message_commands: Vec<Box<dyn MessageCommand<S>>>,
+}
+impl<S: Sync> CommandRegistry<S> {
+    pub fn new(root: &str) -> Self {
+        CommandRegistry {
+            root: root.to_string(),
+            subcommands: vec![],
+            message_commands: vec![],
+        }
+    }
+
+    pub fn subcommand<T>(mut self, subcommand: T) -> Self
+    where
+        T: Subcommand<S> + 'static,
+    {
+        self.subcommands.push(Box::new(subcommand));
+        self
+    }
+
+    pub fn message_command<T>(mut self, command: T) -> Self
+    where
+        T: MessageCommand<S> + 'static,
+    {
+        self.message_commands.push(Box::new(command));
+        self
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Whether a command named name is registered by this registry
+    pub fn handles(&self, name: &str) -> bool {
+        name == self.root
+            || self
+                .message_commands
+                .iter()
+                .any(|command| command.name() == name)
+    }
+
+    /// Builds the root command, described by the `base` message, and the message commands
+    pub fn build(&self, localization: &Localization) -> Vec<Value> {
+        let mut cmd = CreateApplicationCommand::default();
+        localization.apply(&mut cmd, "base", Some(&self.root));
+        for subcommand in &self.subcommands {
+            cmd.create_option(|opt| {
+                create_option(subcommand.as_ref(), opt, localization);
+                opt
+            });
+        }
+
+        let mut commands = vec![Value::from(json::hashmap_to_json_map(cmd.0))];
+        for message_command in &self.message_commands {
+            let mut cmd = CreateApplicationCommand::default();
+            localization
+                .apply_name(&mut cmd, message_command.name_id(), message_command.name())
+                .kind(CommandType::Message);
+            commands.push(Value::from(json::hashmap_to_json_map(cmd.0)));
+        }
+        commands
+    }
+
+    /// Path of subcommand names invoked by command, e.g. `set url`, or the name of a
+    /// message command
+    pub fn path(command: &ApplicationCommandInteraction) -> String {
+        if command.data.kind == CommandType::Message {
+            return command.data.name.clone();
+        }
+
+        let mut path = vec![];
+        let mut options = &command.data.options;
+        while let Some(option) = options.first().filter(|opt| {
+            matches!(
+                opt.kind,
+                CommandOptionType::SubCommand | CommandOptionType::SubCommandGroup
+            )
+        }) {
+            path.push(option.name.as_str());
+            options = &option.options;
+        }
+        path.join(" ")
+    }
+
+    pub async fn run(
+        &self,
+        state: &S,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Result<Option<Response>, CommandError> {
+        if command.data.kind != CommandType::Message {
+            return dispatch(
+                &self.subcommands,
+                state,
+                ctx,
+                command,
+                command.data.options.first(),
+            )
+            .await;
+        }
+
+        let message_command = self
+            .message_commands
+            .iter()
+            .find(|message_command| message_command.name() == command.data.name)
+            .ok_or_else(|| {
+                CommandError::Malformed(format!("unknown command '{}'", command.data.name))
+            })?;
+        let message = match command.data.target() {
+            Some(ResolvedTarget::Message(message)) => message,
+            _ => return Err(CommandError::Malformed("missing target message".into())),
+        };
+
+        message_command.run(state, ctx, command, &message).await
+    }
+
+    /// Suggestions of the subcommand whose option is being typed in interaction
+    pub async fn autocomplete(
+        &self,
+        state: &S,
+        ctx: &Context,
+        interaction: &AutocompleteInteraction,
+    ) -> Result<Vec<String>, CommandError> {
+        let (subcommand, option) = find(&self.subcommands, interaction.data.options.first())
+            .map_err(CommandError::Malformed)?;
+        subcommand
+            .autocomplete(state, ctx, interaction, option)
+            .await
+    }
+}
@@ -0,0 +1,117 @@
+use crate::data::GuildDetails;
+
+use log::info;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+const GUILD_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CooldownLimits {
+    /// minimum time between two plays of the same user in a guild, zero to disable
+    pub user_cooldown: Duration,
+    /// maximum plays in a guild per minute, zero to disable
+    pub guild_plays_per_minute: u64,
+}
+impl Default for CooldownLimits {
+    fn default() -> Self {
+        CooldownLimits {
+            user_cooldown: Duration::from_secs(60),
+            guild_plays_per_minute: 10,
+        }
+    }
+}
+impl CooldownLimits {
+    /// Limits of guild, falling back to these where it does not override them
+    pub fn for_guild(&self, details: &GuildDetails) -> Self {
+        CooldownLimits {
+            user_cooldown: details
+                .user_cooldown_seconds
+                .map_or(self.user_cooldown, Duration::from_secs),
+            guild_plays_per_minute: details
+                .guild_plays_per_minute
+                .unwrap_or(self.guild_plays_per_minute),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Suppression {
+    User,
+    Guild,
+}
+
+#[derive(Default)]
+struct PlayHistory {
+    /// end of cooldown per guild and user
+    user_until: HashMap<(u64, u64), Instant>,
+    guild_plays: HashMap<u64, VecDeque<Instant>>,
+}
+
+#[derive(Default)]
+pub struct Cooldowns {
+    history: Mutex<PlayHistory>,
+    suppressed_by_user: AtomicU64,
+    suppressed_by_guild: AtomicU64,
+}
+impl Cooldowns {
+    /// Records a play of user in guild, unless limits suppress it
+    pub async fn try_play(
+        &self,
+        guild_id: u64,
+        user_id: u64,
+        limits: &CooldownLimits,
+    ) -> Result<(), Suppression> {
+        let now = Instant::now();
+        let mut history = self.history.lock().await;
+
+        history.user_until.retain(|_, until| *until > now);
+        let plays = history.guild_plays.entry(guild_id).or_default();
+        while plays
+            .front()
+            .is_some_and(|play| now.duration_since(*play) >= GUILD_WINDOW)
+        {
+            plays.pop_front();
+        }
+
+        let suppression = if history.user_until.contains_key(&(guild_id, user_id)) {
+            Some(Suppression::User)
+        } else if limits.guild_plays_per_minute > 0
+            && history.guild_plays[&guild_id].len() as u64 >= limits.guild_plays_per_minute
+        {
+            Some(Suppression::Guild)
+        } else {
+            None
+        };
+
+        if let Some(suppression) = suppression {
+            drop(history);
+            let (counter, reason) = match suppression {
+                Suppression::User => (&self.suppressed_by_user, "user cooldown"),
+                Suppression::Guild => (&self.suppressed_by_guild, "guild limit"),
+            };
+            let count = counter.fetch_add(1, Ordering::Relaxed) + 1;
+            info!(
+                "Suppressed chime of user {user_id} in guild {guild_id} by {reason}, {count} suppressed by {reason} so far"
+            );
+            return Err(suppression);
+        }
+
+        if !limits.user_cooldown.is_zero() {
+            history
+                .user_until
+                .insert((guild_id, user_id), now + limits.user_cooldown);
+        }
+        history
+            .guild_plays
+            .entry(guild_id)
+            .or_default()
+            .push_back(now);
+
+        Ok(())
+    }
+}
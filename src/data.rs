@@ -1,23 +1,364 @@
 use log::error;
 use nameof::name_of;
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+use tokio_postgres::types::ToSql;
 
-#[derive(Debug, PartialEq, Eq, Clone, Default)]
+const REVIEW_TABLE_NAME: &str = "ChimeReviews";
+const GUILD_USER_TABLE_NAME: &str = "GuildUsers";
+const AUDIT_TABLE_NAME: &str = "AuditLog";
+const USER_TABLE_NAME: &str = "Users";
+const RECENT_URL_TABLE_NAME: &str = "RecentUrls";
+/// how many of the urls a user set their chime from are kept for suggestions
+const RECENT_URL_LIMIT: i64 = 10;
+const REACHABLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
 pub struct GuildDetails {
     pub id: u64,
     pub blocked_role_id: Option<u64>,
+    /// if set, chimes need approval in this channel before they are played in the guild
+    #[serde(default)]
+    pub moderation_channel_id: Option<u64>,
+    /// overrides cooldown between two chimes of the same user
+    #[serde(default)]
+    pub user_cooldown_seconds: Option<u64>,
+    /// overrides maximum chimes per minute
+    #[serde(default)]
+    pub guild_plays_per_minute: Option<u64>,
+    /// admin actions are posted to this channel
+    #[serde(default)]
+    pub audit_channel_id: Option<u64>,
+    /// default locale of messages in this guild
+    #[serde(default)]
+    pub locale: Option<String>,
 }
 trait TableSchema {
     fn get_schema() -> String;
+    fn get_columns() -> String;
 }
 impl TableSchema for GuildDetails {
     fn get_schema() -> String {
         format!(
-            "{} BIGINT PRIMARY KEY, {} BIGINT",
+            "{} BIGINT PRIMARY KEY, {} BIGINT, {} BIGINT, {} BIGINT, {} BIGINT, {} BIGINT, {} TEXT",
+            name_of!(id in GuildDetails),
+            name_of!(blocked_role_id in GuildDetails),
+            name_of!(moderation_channel_id in GuildDetails),
+            name_of!(user_cooldown_seconds in GuildDetails),
+            name_of!(guild_plays_per_minute in GuildDetails),
+            name_of!(audit_channel_id in GuildDetails),
+            name_of!(locale in GuildDetails)
+        )
+    }
+
+    fn get_columns() -> String {
+        format!(
+            "{}, {}, {}, {}, {}, {}, {}",
             name_of!(id in GuildDetails),
-            name_of!(blocked_role_id in GuildDetails)
+            name_of!(blocked_role_id in GuildDetails),
+            name_of!(moderation_channel_id in GuildDetails),
+            name_of!(user_cooldown_seconds in GuildDetails),
+            name_of!(guild_plays_per_minute in GuildDetails),
+            name_of!(audit_channel_id in GuildDetails),
+            name_of!(locale in GuildDetails)
         )
     }
 }
+impl From<tokio_postgres::Row> for GuildDetails {
+    fn from(row: tokio_postgres::Row) -> Self {
+        GuildDetails {
+            id: row.get::<usize, i64>(0) as u64,
+            blocked_role_id: match row.try_get::<usize, i64>(1) {
+                Ok(val) => Some(val as u64),
+                Err(_) => None,
+            },
+            moderation_channel_id: match row.try_get::<usize, i64>(2) {
+                Ok(val) => Some(val as u64),
+                Err(_) => None,
+            },
+            user_cooldown_seconds: match row.try_get::<usize, i64>(3) {
+                Ok(val) => Some(val as u64),
+                Err(_) => None,
+            },
+            guild_plays_per_minute: match row.try_get::<usize, i64>(4) {
+                Ok(val) => Some(val as u64),
+                Err(_) => None,
+            },
+            audit_channel_id: match row.try_get::<usize, i64>(5) {
+                Ok(val) => Some(val as u64),
+                Err(_) => None,
+            },
+            locale: row.get::<usize, Option<String>>(6),
+        }
+    }
+}
+
+/// Moderation of a single user in a guild
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct GuildUserDetails {
+    pub guild_id: u64,
+    pub user_id: u64,
+    pub muted: bool,
+    /// chime that was cleared in the guild, a new chime of the user plays again
+    pub cleared_chime_id: Option<String>,
+}
+impl TableSchema for GuildUserDetails {
+    fn get_schema() -> String {
+        format!(
+            "{guild} BIGINT NOT NULL, {user} BIGINT NOT NULL, {muted} BOOLEAN NOT NULL DEFAULT FALSE, {cleared} TEXT, PRIMARY KEY ({guild}, {user})",
+            guild = name_of!(guild_id in GuildUserDetails),
+            user = name_of!(user_id in GuildUserDetails),
+            muted = name_of!(muted in GuildUserDetails),
+            cleared = name_of!(cleared_chime_id in GuildUserDetails)
+        )
+    }
+
+    fn get_columns() -> String {
+        format!(
+            "{}, {}, {}, {}",
+            name_of!(guild_id in GuildUserDetails),
+            name_of!(user_id in GuildUserDetails),
+            name_of!(muted in GuildUserDetails),
+            name_of!(cleared_chime_id in GuildUserDetails)
+        )
+    }
+}
+impl From<tokio_postgres::Row> for GuildUserDetails {
+    fn from(row: tokio_postgres::Row) -> Self {
+        GuildUserDetails {
+            guild_id: row.get::<usize, i64>(0) as u64,
+            user_id: row.get::<usize, i64>(1) as u64,
+            muted: row.get::<usize, bool>(2),
+            cleared_chime_id: row.get::<usize, Option<String>>(3),
+        }
+    }
+}
+
+/// Change of a guild setting or moderation of a user by an admin
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub guild_id: u64,
+    pub actor_id: u64,
+    pub action: String,
+    /// user the action was about
+    pub target_id: Option<u64>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    /// unix timestamp
+    pub created_at: i64,
+}
+impl AuditEntry {
+    pub fn new(guild_id: u64, actor_id: u64, action: &str) -> Self {
+        AuditEntry {
+            guild_id,
+            actor_id,
+            action: action.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        }
+    }
+
+    pub fn target(mut self, user_id: u64) -> Self {
+        self.target_id = Some(user_id);
+        self
+    }
+
+    pub fn change(mut self, old_value: Option<String>, new_value: Option<String>) -> Self {
+        self.old_value = old_value;
+        self.new_value = new_value;
+        self
+    }
+}
+impl TableSchema for AuditEntry {
+    fn get_schema() -> String {
+        format!(
+            "{} BIGSERIAL PRIMARY KEY, {} BIGINT NOT NULL, {} BIGINT NOT NULL, {} TEXT NOT NULL, {} BIGINT, {} TEXT, {} TEXT, {} BIGINT NOT NULL",
+            name_of!(id in AuditEntry),
+            name_of!(guild_id in AuditEntry),
+            name_of!(actor_id in AuditEntry),
+            name_of!(action in AuditEntry),
+            name_of!(target_id in AuditEntry),
+            name_of!(old_value in AuditEntry),
+            name_of!(new_value in AuditEntry),
+            name_of!(created_at in AuditEntry)
+        )
+    }
+
+    fn get_columns() -> String {
+        format!(
+            "{}, {}, {}, {}, {}, {}, {}, {}",
+            name_of!(id in AuditEntry),
+            name_of!(guild_id in AuditEntry),
+            name_of!(actor_id in AuditEntry),
+            name_of!(action in AuditEntry),
+            name_of!(target_id in AuditEntry),
+            name_of!(old_value in AuditEntry),
+            name_of!(new_value in AuditEntry),
+            name_of!(created_at in AuditEntry)
+        )
+    }
+}
+impl From<tokio_postgres::Row> for AuditEntry {
+    fn from(row: tokio_postgres::Row) -> Self {
+        AuditEntry {
+            id: row.get::<usize, i64>(0),
+            guild_id: row.get::<usize, i64>(1) as u64,
+            actor_id: row.get::<usize, i64>(2) as u64,
+            action: row.get::<usize, String>(3),
+            target_id: row.get::<usize, Option<i64>>(4).map(|id| id as u64),
+            old_value: row.get::<usize, Option<String>>(5),
+            new_value: row.get::<usize, Option<String>>(6),
+            created_at: row.get::<usize, i64>(7),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+impl ReviewStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStatus::Pending => "pending",
+            ReviewStatus::Approved => "approved",
+            ReviewStatus::Rejected => "rejected",
+        }
+    }
+}
+impl FromStr for ReviewStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ReviewStatus::Pending),
+            "approved" => Ok(ReviewStatus::Approved),
+            "rejected" => Ok(ReviewStatus::Rejected),
+            other => Err(format!("Unknown review status '{other}'")),
+        }
+    }
+}
+
+/// Decision of a guild's moderators about one specific chime of a user
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ChimeReview {
+    pub id: i64,
+    pub guild_id: u64,
+    pub user_id: u64,
+    pub chime_id: String,
+    pub status: ReviewStatus,
+}
+impl TableSchema for ChimeReview {
+    fn get_schema() -> String {
+        format!(
+            "{id} BIGSERIAL PRIMARY KEY, {guild} BIGINT NOT NULL, {user} BIGINT NOT NULL, {chime} TEXT NOT NULL, {status} TEXT NOT NULL, UNIQUE ({guild}, {user}, {chime})",
+            id = name_of!(id in ChimeReview),
+            guild = name_of!(guild_id in ChimeReview),
+            user = name_of!(user_id in ChimeReview),
+            chime = name_of!(chime_id in ChimeReview),
+            status = name_of!(status in ChimeReview)
+        )
+    }
+
+    fn get_columns() -> String {
+        format!(
+            "{}, {}, {}, {}, {}",
+            name_of!(id in ChimeReview),
+            name_of!(guild_id in ChimeReview),
+            name_of!(user_id in ChimeReview),
+            name_of!(chime_id in ChimeReview),
+            name_of!(status in ChimeReview)
+        )
+    }
+}
+impl From<tokio_postgres::Row> for ChimeReview {
+    fn from(row: tokio_postgres::Row) -> Self {
+        ChimeReview {
+            id: row.get::<usize, i64>(0),
+            guild_id: row.get::<usize, i64>(1) as u64,
+            user_id: row.get::<usize, i64>(2) as u64,
+            chime_id: row.get::<usize, String>(3),
+            status: row
+                .get::<usize, String>(4)
+                .parse()
+                .unwrap_or(ReviewStatus::Pending),
+        }
+    }
+}
+
+/// Preferences of a user, independent of guilds
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct UserDetails {
+    pub id: u64,
+    /// overrides the locale of the guild and the client
+    pub locale: Option<String>,
+}
+impl TableSchema for UserDetails {
+    fn get_schema() -> String {
+        format!(
+            "{} BIGINT PRIMARY KEY, {} TEXT",
+            name_of!(id in UserDetails),
+            name_of!(locale in UserDetails)
+        )
+    }
+
+    fn get_columns() -> String {
+        format!(
+            "{}, {}",
+            name_of!(id in UserDetails),
+            name_of!(locale in UserDetails)
+        )
+    }
+}
+impl From<tokio_postgres::Row> for UserDetails {
+    fn from(row: tokio_postgres::Row) -> Self {
+        UserDetails {
+            id: row.get::<usize, i64>(0) as u64,
+            locale: row.get::<usize, Option<String>>(1),
+        }
+    }
+}
+
+/// Url a user set their chime from, suggested when they set one again
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct RecentUrl {
+    pub user_id: u64,
+    pub url: String,
+    /// unix timestamp
+    pub used_at: i64,
+}
+impl TableSchema for RecentUrl {
+    fn get_schema() -> String {
+        format!(
+            "{user} BIGINT NOT NULL, {url} TEXT NOT NULL, {used} BIGINT NOT NULL, PRIMARY KEY ({user}, {url})",
+            user = name_of!(user_id in RecentUrl),
+            url = name_of!(url in RecentUrl),
+            used = name_of!(used_at in RecentUrl)
+        )
+    }
+
+    fn get_columns() -> String {
+        format!(
+            "{}, {}, {}",
+            name_of!(user_id in RecentUrl),
+            name_of!(url in RecentUrl),
+            name_of!(used_at in RecentUrl)
+        )
+    }
+}
+impl From<tokio_postgres::Row> for RecentUrl {
+    fn from(row: tokio_postgres::Row) -> Self {
+        RecentUrl {
+            user_id: row.get::<usize, i64>(0) as u64,
+            url: row.get::<usize, String>(1),
+            used_at: row.get::<usize, i64>(2),
+        }
+    }
+}
 
 pub struct DatabaseInterface {
     client: tokio_postgres::Client,
@@ -43,6 +384,95 @@ impl DatabaseInterface {
             )
             .await
             .expect("Query ensuring table exists failed!");
+
+        // columns added after the table was first created
+        for (column, column_type) in [
+            (name_of!(moderation_channel_id in GuildDetails), "BIGINT"),
+            (name_of!(user_cooldown_seconds in GuildDetails), "BIGINT"),
+            (name_of!(guild_plays_per_minute in GuildDetails), "BIGINT"),
+            (name_of!(audit_channel_id in GuildDetails), "BIGINT"),
+            (name_of!(locale in GuildDetails), "TEXT"),
+        ] {
+            self.client
+                .execute_raw::<_, _, &[&str]>(
+                    &format!(
+                        "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}",
+                        self.table_name, column, column_type
+                    ),
+                    &[],
+                )
+                .await
+                .expect("Query migrating table failed!");
+        }
+
+        self.client
+            .execute_raw::<_, _, &[&str]>(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({})",
+                    REVIEW_TABLE_NAME,
+                    ChimeReview::get_schema()
+                ),
+                &[],
+            )
+            .await
+            .expect("Query ensuring review table exists failed!");
+
+        self.client
+            .execute_raw::<_, _, &[&str]>(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({})",
+                    GUILD_USER_TABLE_NAME,
+                    GuildUserDetails::get_schema()
+                ),
+                &[],
+            )
+            .await
+            .expect("Query ensuring guild user table exists failed!");
+
+        self.client
+            .execute_raw::<_, _, &[&str]>(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({})",
+                    AUDIT_TABLE_NAME,
+                    AuditEntry::get_schema()
+                ),
+                &[],
+            )
+            .await
+            .expect("Query ensuring audit table exists failed!");
+
+        self.client
+            .execute_raw::<_, _, &[&str]>(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({})",
+                    USER_TABLE_NAME,
+                    UserDetails::get_schema()
+                ),
+                &[],
+            )
+            .await
+            .expect("Query ensuring user table exists failed!");
+
+        self.client
+            .execute_raw::<_, _, &[&str]>(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {} ({})",
+                    RECENT_URL_TABLE_NAME,
+                    RecentUrl::get_schema()
+                ),
+                &[],
+            )
+            .await
+            .expect("Query ensuring recent url table exists failed!");
+    }
+
+    /// Whether the database answers within REACHABLE_TIMEOUT, a stalled one is unreachable
+    pub async fn is_reachable(&self) -> bool {
+        !self.client.is_closed()
+            && matches!(
+                tokio::time::timeout(REACHABLE_TIMEOUT, self.client.simple_query("SELECT 1")).await,
+                Ok(Ok(_))
+            )
     }
 
     pub async fn get_guild_details(&self, guild_id: &u64) -> Option<GuildDetails> {
@@ -50,9 +480,8 @@ impl DatabaseInterface {
             .client
             .query_opt(
                 &format!(
-                    "SELECT {}, {} FROM {} WHERE id = $1::BIGINT",
-                    name_of!(id in GuildDetails),
-                    name_of!(blocked_role_id in GuildDetails),
+                    "SELECT {} FROM {} WHERE id = $1::BIGINT",
+                    GuildDetails::get_columns(),
                     self.table_name
                 ),
                 &[&(*guild_id as i64)],
@@ -64,15 +493,26 @@ impl DatabaseInterface {
             })
             .ok()??;
 
-        Some(GuildDetails {
-            id: row.get::<usize, i64>(0) as u64,
-            blocked_role_id: match row.try_get::<usize, i64>(1) {
-                Ok(val) => Some(val as u64),
-                Err(_) => None,
-            },
-        })
+        Some(row.into())
     }
 
+    pub async fn get_all_guild_details(&self) -> Result<Vec<GuildDetails>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {}",
+                    GuildDetails::get_columns(),
+                    self.table_name
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(GuildDetails::from).collect())
+    }
+
+    /// Replaces all settings of guild, admins change single settings through the setters below
     pub async fn set_guild_details(
         &self,
         details: GuildDetails,
@@ -80,17 +520,783 @@ impl DatabaseInterface {
         self.client
         .execute(
             &format!(
-                "INSERT INTO {table} ({key},{value}) VALUES ($1::BIGINT, $2::BIGINT) ON CONFLICT ({key}) DO UPDATE SET {value} = EXCLUDED.{value}", 
+                "INSERT INTO {table} ({columns}) VALUES ($1::BIGINT, $2::BIGINT, $3::BIGINT, $4::BIGINT, $5::BIGINT, $6::BIGINT, $7) ON CONFLICT ({key}) DO UPDATE SET {role} = EXCLUDED.{role}, {channel} = EXCLUDED.{channel}, {cooldown} = EXCLUDED.{cooldown}, {plays} = EXCLUDED.{plays}, {audit} = EXCLUDED.{audit}, {locale} = EXCLUDED.{locale}", 
                 table = self.table_name,
+                columns = GuildDetails::get_columns(),
                 key = name_of!(id in GuildDetails),
-                value = name_of!(blocked_role_id in GuildDetails)
+                role = name_of!(blocked_role_id in GuildDetails),
+                channel = name_of!(moderation_channel_id in GuildDetails),
+                cooldown = name_of!(user_cooldown_seconds in GuildDetails),
+                plays = name_of!(guild_plays_per_minute in GuildDetails),
+                audit = name_of!(audit_channel_id in GuildDetails),
+                locale = name_of!(locale in GuildDetails)
             ),
             &[
                 &(details.id as i64),
-                &details.blocked_role_id.map(|unsigned| unsigned as i64)
+                &details.blocked_role_id.map(|unsigned| unsigned as i64),
+                &details.moderation_channel_id.map(|unsigned| unsigned as i64),
+                &details.user_cooldown_seconds.map(|unsigned| unsigned as i64),
+                &details.guild_plays_per_minute.map(|unsigned| unsigned as i64),
+                &details.audit_channel_id.map(|unsigned| unsigned as i64),
+                &details.locale
             ]
         ).await?;
 
         Ok(())
     }
+
+    /// Sets columns of guild only, so changes to its other settings in the meantime persist
+    async fn set_guild_columns(
+        &self,
+        guild_id: u64,
+        columns: &[&str],
+        values: &[&(dyn ToSql + Sync)],
+    ) -> Result<(), tokio_postgres::Error> {
+        let id = guild_id as i64;
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&id];
+        params.extend_from_slice(values);
+
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {table} ({key}, {columns}) VALUES ($1::BIGINT, {values}) ON CONFLICT ({key}) DO UPDATE SET {updates}",
+                    table = self.table_name,
+                    key = name_of!(id in GuildDetails),
+                    columns = columns.join(", "),
+                    values = (2..=columns.len() + 1)
+                        .map(|index| format!("${index}"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    updates = columns
+                        .iter()
+                        .map(|column| format!("{column} = EXCLUDED.{column}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                &params,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_blocked_role_id(
+        &self,
+        guild_id: u64,
+        role_id: Option<u64>,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_guild_columns(
+            guild_id,
+            &[name_of!(blocked_role_id in GuildDetails)],
+            &[&role_id.map(|unsigned| unsigned as i64)],
+        )
+        .await
+    }
+
+    pub async fn set_moderation_channel_id(
+        &self,
+        guild_id: u64,
+        channel_id: Option<u64>,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_guild_columns(
+            guild_id,
+            &[name_of!(moderation_channel_id in GuildDetails)],
+            &[&channel_id.map(|unsigned| unsigned as i64)],
+        )
+        .await
+    }
+
+    pub async fn set_user_cooldown_seconds(
+        &self,
+        guild_id: u64,
+        seconds: Option<u64>,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_guild_columns(
+            guild_id,
+            &[name_of!(user_cooldown_seconds in GuildDetails)],
+            &[&seconds.map(|unsigned| unsigned as i64)],
+        )
+        .await
+    }
+
+    pub async fn set_guild_plays_per_minute(
+        &self,
+        guild_id: u64,
+        plays: Option<u64>,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_guild_columns(
+            guild_id,
+            &[name_of!(guild_plays_per_minute in GuildDetails)],
+            &[&plays.map(|unsigned| unsigned as i64)],
+        )
+        .await
+    }
+
+    /// Sets both cooldowns at once, as admins change them together
+    pub async fn set_guild_cooldowns(
+        &self,
+        guild_id: u64,
+        user_cooldown_seconds: Option<u64>,
+        guild_plays_per_minute: Option<u64>,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_guild_columns(
+            guild_id,
+            &[
+                name_of!(user_cooldown_seconds in GuildDetails),
+                name_of!(guild_plays_per_minute in GuildDetails),
+            ],
+            &[
+                &user_cooldown_seconds.map(|unsigned| unsigned as i64),
+                &guild_plays_per_minute.map(|unsigned| unsigned as i64),
+            ],
+        )
+        .await
+    }
+
+    pub async fn set_audit_channel_id(
+        &self,
+        guild_id: u64,
+        channel_id: Option<u64>,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_guild_columns(
+            guild_id,
+            &[name_of!(audit_channel_id in GuildDetails)],
+            &[&channel_id.map(|unsigned| unsigned as i64)],
+        )
+        .await
+    }
+
+    pub async fn set_guild_locale(
+        &self,
+        guild_id: u64,
+        locale: Option<String>,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_guild_columns(guild_id, &[name_of!(locale in GuildDetails)], &[&locale])
+            .await
+    }
+
+    pub async fn add_audit_entry(&self, entry: &AuditEntry) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {AUDIT_TABLE_NAME} ({}, {}, {}, {}, {}, {}, {}) VALUES ($1::BIGINT, $2::BIGINT, $3, $4::BIGINT, $5, $6, $7::BIGINT)",
+                    name_of!(guild_id in AuditEntry),
+                    name_of!(actor_id in AuditEntry),
+                    name_of!(action in AuditEntry),
+                    name_of!(target_id in AuditEntry),
+                    name_of!(old_value in AuditEntry),
+                    name_of!(new_value in AuditEntry),
+                    name_of!(created_at in AuditEntry)
+                ),
+                &[
+                    &(entry.guild_id as i64),
+                    &(entry.actor_id as i64),
+                    &entry.action,
+                    &entry.target_id.map(|unsigned| unsigned as i64),
+                    &entry.old_value,
+                    &entry.new_value,
+                    &entry.created_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Latest entries of guild, newest first
+    pub async fn get_audit_entries(
+        &self,
+        guild_id: u64,
+        limit: i64,
+    ) -> Result<Vec<AuditEntry>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {columns} FROM {AUDIT_TABLE_NAME} WHERE {guild} = $1::BIGINT ORDER BY {id} DESC LIMIT $2::BIGINT",
+                    columns = AuditEntry::get_columns(),
+                    guild = name_of!(guild_id in AuditEntry),
+                    id = name_of!(id in AuditEntry)
+                ),
+                &[&(guild_id as i64), &limit],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(AuditEntry::from).collect())
+    }
+
+    /// Entries where user acted or was acted upon
+    pub async fn get_audit_entries_of_user(
+        &self,
+        user_id: u64,
+    ) -> Result<Vec<AuditEntry>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {columns} FROM {AUDIT_TABLE_NAME} WHERE {actor} = $1::BIGINT OR {target} = $1::BIGINT ORDER BY {id}",
+                    columns = AuditEntry::get_columns(),
+                    actor = name_of!(actor_id in AuditEntry),
+                    target = name_of!(target_id in AuditEntry),
+                    id = name_of!(id in AuditEntry)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(AuditEntry::from).collect())
+    }
+
+    /// Removes user and the changes made to them from entries about them, returns their guilds
+    pub async fn anonymize_audit_entries_of_target(
+        &self,
+        user_id: u64,
+    ) -> Result<Vec<u64>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "UPDATE {AUDIT_TABLE_NAME} SET {target} = NULL, {old} = NULL, {new} = NULL WHERE {target} = $1::BIGINT RETURNING {guild}",
+                    target = name_of!(target_id in AuditEntry),
+                    old = name_of!(old_value in AuditEntry),
+                    new = name_of!(new_value in AuditEntry),
+                    guild = name_of!(guild_id in AuditEntry)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| row.get::<usize, i64>(0) as u64)
+            .collect())
+    }
+
+    pub async fn get_all_audit_entries(&self) -> Result<Vec<AuditEntry>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {AUDIT_TABLE_NAME} ORDER BY {}",
+                    AuditEntry::get_columns(),
+                    name_of!(id in AuditEntry)
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(AuditEntry::from).collect())
+    }
+
+    /// Inserts entry keeping its id, replacing the entry with the same id
+    pub async fn import_audit_entry(
+        &self,
+        entry: &AuditEntry,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {AUDIT_TABLE_NAME} ({columns}) VALUES ($1::BIGINT, $2::BIGINT, $3::BIGINT, $4, $5::BIGINT, $6, $7, $8::BIGINT) ON CONFLICT ({id}) DO UPDATE SET {guild} = EXCLUDED.{guild}, {actor} = EXCLUDED.{actor}, {action} = EXCLUDED.{action}, {target} = EXCLUDED.{target}, {old} = EXCLUDED.{old}, {new} = EXCLUDED.{new}, {created} = EXCLUDED.{created}",
+                    columns = AuditEntry::get_columns(),
+                    id = name_of!(id in AuditEntry),
+                    guild = name_of!(guild_id in AuditEntry),
+                    actor = name_of!(actor_id in AuditEntry),
+                    action = name_of!(action in AuditEntry),
+                    target = name_of!(target_id in AuditEntry),
+                    old = name_of!(old_value in AuditEntry),
+                    new = name_of!(new_value in AuditEntry),
+                    created = name_of!(created_at in AuditEntry)
+                ),
+                &[
+                    &entry.id,
+                    &(entry.guild_id as i64),
+                    &(entry.actor_id as i64),
+                    &entry.action,
+                    &entry.target_id.map(|unsigned| unsigned as i64),
+                    &entry.old_value,
+                    &entry.new_value,
+                    &entry.created_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_all_user_details(&self) -> Result<Vec<UserDetails>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {USER_TABLE_NAME}",
+                    UserDetails::get_columns()
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(UserDetails::from).collect())
+    }
+
+    pub async fn get_user_details(&self, user_id: u64) -> Option<UserDetails> {
+        let row = self
+            .client
+            .query_opt(
+                &format!(
+                    "SELECT {} FROM {USER_TABLE_NAME} WHERE {} = $1::BIGINT",
+                    UserDetails::get_columns(),
+                    name_of!(id in UserDetails)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await
+            .map_err(|err| {
+                error!("Could not get details for user {user_id} from database: {err:?}");
+                err
+            })
+            .ok()??;
+
+        Some(row.into())
+    }
+
+    pub async fn set_user_details(
+        &self,
+        details: UserDetails,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {USER_TABLE_NAME} ({columns}) VALUES ($1::BIGINT, $2) ON CONFLICT ({key}) DO UPDATE SET {locale} = EXCLUDED.{locale}",
+                    columns = UserDetails::get_columns(),
+                    key = name_of!(id in UserDetails),
+                    locale = name_of!(locale in UserDetails)
+                ),
+                &[&(details.id as i64), &details.locale],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_user_details(&self, user_id: u64) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {USER_TABLE_NAME} WHERE {} = $1::BIGINT",
+                    name_of!(id in UserDetails)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_guild_user_details(
+        &self,
+        guild_id: u64,
+        user_id: u64,
+    ) -> Option<GuildUserDetails> {
+        let row = self
+            .client
+            .query_opt(
+                &format!(
+                    "SELECT {columns} FROM {GUILD_USER_TABLE_NAME} WHERE {guild} = $1::BIGINT AND {user} = $2::BIGINT",
+                    columns = GuildUserDetails::get_columns(),
+                    guild = name_of!(guild_id in GuildUserDetails),
+                    user = name_of!(user_id in GuildUserDetails)
+                ),
+                &[&(guild_id as i64), &(user_id as i64)],
+            )
+            .await
+            .map_err(|err| {
+                error!("Could not get details of user {user_id} in guild {guild_id} from database: {err:?}");
+                err
+            })
+            .ok()??;
+
+        Some(row.into())
+    }
+
+    pub async fn get_all_guild_user_details(
+        &self,
+    ) -> Result<Vec<GuildUserDetails>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {GUILD_USER_TABLE_NAME}",
+                    GuildUserDetails::get_columns()
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(GuildUserDetails::from).collect())
+    }
+
+    pub async fn get_guild_user_details_of_user(
+        &self,
+        user_id: u64,
+    ) -> Result<Vec<GuildUserDetails>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {GUILD_USER_TABLE_NAME} WHERE {} = $1::BIGINT",
+                    GuildUserDetails::get_columns(),
+                    name_of!(user_id in GuildUserDetails)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(GuildUserDetails::from).collect())
+    }
+
+    pub async fn delete_guild_user_details_of_user(
+        &self,
+        user_id: u64,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {GUILD_USER_TABLE_NAME} WHERE {} = $1::BIGINT",
+                    name_of!(user_id in GuildUserDetails)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_guild_user_details(
+        &self,
+        details: GuildUserDetails,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {GUILD_USER_TABLE_NAME} ({columns}) VALUES ($1::BIGINT, $2::BIGINT, $3, $4) ON CONFLICT ({guild}, {user}) DO UPDATE SET {muted} = EXCLUDED.{muted}, {cleared} = EXCLUDED.{cleared}",
+                    columns = GuildUserDetails::get_columns(),
+                    guild = name_of!(guild_id in GuildUserDetails),
+                    user = name_of!(user_id in GuildUserDetails),
+                    muted = name_of!(muted in GuildUserDetails),
+                    cleared = name_of!(cleared_chime_id in GuildUserDetails)
+                ),
+                &[
+                    &(details.guild_id as i64),
+                    &(details.user_id as i64),
+                    &details.muted,
+                    &details.cleared_chime_id,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_review_status(
+        &self,
+        guild_id: u64,
+        user_id: u64,
+        chime_id: &str,
+    ) -> Option<ReviewStatus> {
+        let row = self
+            .client
+            .query_opt(
+                &format!(
+                    "SELECT {status} FROM {REVIEW_TABLE_NAME} WHERE {guild} = $1::BIGINT AND {user} = $2::BIGINT AND {chime} = $3",
+                    status = name_of!(status in ChimeReview),
+                    guild = name_of!(guild_id in ChimeReview),
+                    user = name_of!(user_id in ChimeReview),
+                    chime = name_of!(chime_id in ChimeReview)
+                ),
+                &[&(guild_id as i64), &(user_id as i64), &chime_id],
+            )
+            .await
+            .map_err(|err| {
+                error!("Could not get review of user {user_id} in guild {guild_id} from database: {err:?}");
+                err
+            })
+            .ok()??;
+
+        row.get::<_, String>(0).parse().ok()
+    }
+
+    /// Creates a pending review, returns `None` if the chime was submitted before
+    pub async fn create_review(
+        &self,
+        guild_id: u64,
+        user_id: u64,
+        chime_id: &str,
+    ) -> Result<Option<i64>, tokio_postgres::Error> {
+        let row = self
+            .client
+            .query_opt(
+                &format!(
+                    "INSERT INTO {REVIEW_TABLE_NAME} ({guild}, {user}, {chime}, {status}) VALUES ($1::BIGINT, $2::BIGINT, $3, $4) ON CONFLICT DO NOTHING RETURNING {id}",
+                    guild = name_of!(guild_id in ChimeReview),
+                    user = name_of!(user_id in ChimeReview),
+                    chime = name_of!(chime_id in ChimeReview),
+                    status = name_of!(status in ChimeReview),
+                    id = name_of!(id in ChimeReview)
+                ),
+                &[
+                    &(guild_id as i64),
+                    &(user_id as i64),
+                    &chime_id,
+                    &ReviewStatus::Pending.as_str(),
+                ],
+            )
+            .await?;
+
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    /// Decides a pending review, returns `None` if it does not exist or was decided already
+    pub async fn decide_review(
+        &self,
+        review_id: i64,
+        status: ReviewStatus,
+    ) -> Result<Option<ChimeReview>, tokio_postgres::Error> {
+        let row = self
+            .client
+            .query_opt(
+                &format!(
+                    "UPDATE {REVIEW_TABLE_NAME} SET {status} = $2 WHERE {id} = $1 AND {status} = $3 RETURNING {columns}",
+                    status = name_of!(status in ChimeReview),
+                    id = name_of!(id in ChimeReview),
+                    columns = ChimeReview::get_columns()
+                ),
+                &[
+                    &review_id,
+                    &status.as_str(),
+                    &ReviewStatus::Pending.as_str(),
+                ],
+            )
+            .await?;
+
+        Ok(row.map(ChimeReview::from))
+    }
+
+    pub async fn delete_review(&self, review_id: i64) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {REVIEW_TABLE_NAME} WHERE {} = $1",
+                    name_of!(id in ChimeReview)
+                ),
+                &[&review_id],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_all_reviews(&self) -> Result<Vec<ChimeReview>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {REVIEW_TABLE_NAME} ORDER BY {}",
+                    ChimeReview::get_columns(),
+                    name_of!(id in ChimeReview)
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(ChimeReview::from).collect())
+    }
+
+    /// Inserts review keeping its id, replacing reviews with the same id or of the same chime
+    pub async fn import_review(&self, review: &ChimeReview) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {REVIEW_TABLE_NAME} WHERE {id} = $1 OR ({guild} = $2::BIGINT AND {user} = $3::BIGINT AND {chime} = $4)",
+                    id = name_of!(id in ChimeReview),
+                    guild = name_of!(guild_id in ChimeReview),
+                    user = name_of!(user_id in ChimeReview),
+                    chime = name_of!(chime_id in ChimeReview)
+                ),
+                &[
+                    &review.id,
+                    &(review.guild_id as i64),
+                    &(review.user_id as i64),
+                    &review.chime_id,
+                ],
+            )
+            .await?;
+
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {REVIEW_TABLE_NAME} ({}) VALUES ($1, $2::BIGINT, $3::BIGINT, $4, $5)",
+                    ChimeReview::get_columns()
+                ),
+                &[
+                    &review.id,
+                    &(review.guild_id as i64),
+                    &(review.user_id as i64),
+                    &review.chime_id,
+                    &review.status.as_str(),
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Continues ids of reviews and audit entries after the highest one, as imports insert ids
+    pub async fn reset_id_sequences(&self) -> Result<(), tokio_postgres::Error> {
+        for (table, id) in [
+            (REVIEW_TABLE_NAME, name_of!(id in ChimeReview)),
+            (AUDIT_TABLE_NAME, name_of!(id in AuditEntry)),
+        ] {
+            self.client
+                .execute(
+                    &format!(
+                        "SELECT setval(pg_get_serial_sequence('{table}', '{id}'), COALESCE(MAX({id}), 0) + 1, false) FROM {table}"
+                    ),
+                    &[],
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_reviews_of_user(
+        &self,
+        user_id: u64,
+    ) -> Result<Vec<ChimeReview>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {REVIEW_TABLE_NAME} WHERE {} = $1::BIGINT",
+                    ChimeReview::get_columns(),
+                    name_of!(user_id in ChimeReview)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(ChimeReview::from).collect())
+    }
+
+    pub async fn delete_reviews_of_user(&self, user_id: u64) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {REVIEW_TABLE_NAME} WHERE {} = $1::BIGINT",
+                    name_of!(user_id in ChimeReview)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_recent_url(&self, recent: &RecentUrl) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "INSERT INTO {RECENT_URL_TABLE_NAME} ({columns}) VALUES ($1::BIGINT, $2, $3::BIGINT) ON CONFLICT ({user}, {url}) DO UPDATE SET {used} = EXCLUDED.{used}",
+                    columns = RecentUrl::get_columns(),
+                    user = name_of!(user_id in RecentUrl),
+                    url = name_of!(url in RecentUrl),
+                    used = name_of!(used_at in RecentUrl)
+                ),
+                &[&(recent.user_id as i64), &recent.url, &recent.used_at],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remembers that user set their chime from url, forgetting all but the latest ones
+    pub async fn add_recent_url(
+        &self,
+        user_id: u64,
+        url: &str,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.set_recent_url(&RecentUrl {
+            user_id,
+            url: url.to_string(),
+            used_at: chrono::Utc::now().timestamp(),
+        })
+        .await?;
+
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {RECENT_URL_TABLE_NAME} WHERE {user} = $1::BIGINT AND {url} NOT IN (SELECT {url} FROM {RECENT_URL_TABLE_NAME} WHERE {user} = $1::BIGINT ORDER BY {used} DESC LIMIT $2::BIGINT)",
+                    user = name_of!(user_id in RecentUrl),
+                    url = name_of!(url in RecentUrl),
+                    used = name_of!(used_at in RecentUrl)
+                ),
+                &[&(user_id as i64), &RECENT_URL_LIMIT],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_all_recent_urls(&self) -> Result<Vec<RecentUrl>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM {RECENT_URL_TABLE_NAME}",
+                    RecentUrl::get_columns()
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(RecentUrl::from).collect())
+    }
+
+    /// Urls user set their chime from, newest first
+    pub async fn get_recent_urls(
+        &self,
+        user_id: u64,
+    ) -> Result<Vec<RecentUrl>, tokio_postgres::Error> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {columns} FROM {RECENT_URL_TABLE_NAME} WHERE {user} = $1::BIGINT ORDER BY {used} DESC",
+                    columns = RecentUrl::get_columns(),
+                    user = name_of!(user_id in RecentUrl),
+                    used = name_of!(used_at in RecentUrl)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(RecentUrl::from).collect())
+    }
+
+    pub async fn delete_recent_urls_of_user(
+        &self,
+        user_id: u64,
+    ) -> Result<(), tokio_postgres::Error> {
+        self.client
+            .execute(
+                &format!(
+                    "DELETE FROM {RECENT_URL_TABLE_NAME} WHERE {} = $1::BIGINT",
+                    name_of!(user_id in RecentUrl)
+                ),
+                &[&(user_id as i64)],
+            )
+            .await?;
+
+        Ok(())
+    }
 }
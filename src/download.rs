@@ -0,0 +1,517 @@
+use log::warn;
+use reqwest::{header::LOCATION, redirect, Response};
+use std::{
+    error::Error,
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+use tokio::{fs::File, io::AsyncWriteExt, time::timeout};
+use url::{Host, Url};
+
+#[derive(Debug)]
+pub enum DownloadError {
+    Scheme,
+    Domain,
+    Address(IpAddr),
+    Redirects,
+    Resolve,
+    TooLarge,
+    Timeout,
+    Request(reqwest::Error),
+    Io(std::io::Error),
+}
+impl Error for DownloadError {}
+impl Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadError::Scheme => write!(f, "scheme not allowed"),
+            DownloadError::Domain => write!(f, "domain not allowed"),
+            DownloadError::Address(addr) => write!(f, "address {addr} not allowed"),
+            DownloadError::Redirects => write!(f, "too many redirects"),
+            DownloadError::Resolve => write!(f, "could not resolve host"),
+            DownloadError::TooLarge => write!(f, "file too large"),
+            DownloadError::Timeout => write!(f, "timed out"),
+            DownloadError::Request(why) => write!(f, "{why}"),
+            DownloadError::Io(why) => write!(f, "{why}"),
+        }
+    }
+}
+
+/// Guards downloads of user supplied urls against requests into private networks
+pub struct DownloadPolicy {
+    pub max_redirects: usize,
+    /// if not empty, only these domains and their subdomains are allowed
+    pub allowed_domains: Vec<String>,
+    pub denied_domains: Vec<String>,
+    /// allows loopback, private and link-local addresses, e.g. for testing
+    pub allow_private_addresses: bool,
+    pub connect_timeout: Duration,
+    /// maximum time to wait for the response or its next chunk
+    pub read_timeout: Duration,
+}
+impl Default for DownloadPolicy {
+    fn default() -> Self {
+        DownloadPolicy {
+            max_redirects: 5,
+            allowed_domains: vec![],
+            denied_domains: vec![],
+            allow_private_addresses: false,
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_secs(10),
+        }
+    }
+}
+impl DownloadPolicy {
+    pub fn parse_domains(domains: &str) -> Vec<String> {
+        domains
+            .split(',')
+            .map(|domain| domain.trim().trim_matches('.').to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect()
+    }
+
+    /// Whether domain or one of its parents is in list
+    pub fn matches(domain: &str, list: &[String]) -> bool {
+        list.iter().any(|entry| {
+            domain == entry
+                || domain
+                    .strip_suffix(entry.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+
+    fn is_public_v4(addr: &Ipv4Addr) -> bool {
+        let [a, b, ..] = addr.octets();
+        !(addr.is_private()
+            || addr.is_loopback()
+            || addr.is_link_local()
+            || addr.is_unspecified()
+            || addr.is_broadcast()
+            || addr.is_documentation()
+            || addr.is_multicast()
+            || a == 0
+            // shared address space
+            || (a == 100 && (64..128).contains(&b))
+            // reserved
+            || a >= 240)
+    }
+
+    /// IPv4 address that addr reaches through a transition mechanism, if it embeds one
+    fn embedded_v4(addr: &Ipv6Addr) -> Option<Ipv4Addr> {
+        let v4 = |high: u16, low: u16| Ipv4Addr::from((u32::from(high) << 16) | u32::from(low));
+
+        match addr.segments() {
+            // IPv4-mapped ::ffff:a.b.c.d and IPv4-compatible ::a.b.c.d
+            [0, 0, 0, 0, 0, 0 | 0xffff, high, low] => Some(v4(high, low)),
+            // NAT64 64:ff9b::/96
+            [0x64, 0xff9b, 0, 0, 0, 0, high, low] => Some(v4(high, low)),
+            // 6to4 2002::/16
+            [0x2002, high, low, ..] => Some(v4(high, low)),
+            _ => None,
+        }
+    }
+
+    fn is_public_v6(addr: &Ipv6Addr) -> bool {
+        if let Some(v4) = Self::embedded_v4(addr) {
+            return Self::is_public_v4(&v4);
+        }
+        let [first, second, ..] = addr.segments();
+        !(addr.is_loopback()
+            || addr.is_unspecified()
+            || addr.is_multicast()
+            // unique local
+            || (first & 0xfe00) == 0xfc00
+            // link-local
+            || (first & 0xffc0) == 0xfe80
+            // site-local
+            || (first & 0xffc0) == 0xfec0
+            // documentation
+            || (first == 0x2001 && second == 0x0db8)
+            // Teredo, whose IPv4 address is obfuscated
+            || (first == 0x2001 && second == 0)
+            // local-use NAT64
+            || (first == 0x64 && second == 0xff9b)
+            // discard-only
+            || first == 0x0100)
+    }
+
+    fn is_allowed_address(&self, addr: &IpAddr) -> bool {
+        self.allow_private_addresses
+            || match addr {
+                IpAddr::V4(v4) => Self::is_public_v4(v4),
+                IpAddr::V6(v6) => Self::is_public_v6(v6),
+            }
+    }
+
+    /// Checks url and returns the address to connect to
+    pub async fn check(&self, url: &Url) -> Result<SocketAddr, DownloadError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(DownloadError::Scheme);
+        }
+        let port = url.port_or_known_default().ok_or(DownloadError::Scheme)?;
+
+        let addrs: Vec<SocketAddr> = match url.host() {
+            Some(Host::Domain(domain)) => {
+                let domain = domain.trim_end_matches('.').to_lowercase();
+                if Self::matches(&domain, &self.denied_domains)
+                    || (!self.allowed_domains.is_empty()
+                        && !Self::matches(&domain, &self.allowed_domains))
+                {
+                    return Err(DownloadError::Domain);
+                }
+
+                let resolved = tokio::net::lookup_host((domain.as_str(), port))
+                    .await
+                    .map_err(|why| {
+                        warn!("Could not resolve {domain}: {why}");
+                        DownloadError::Resolve
+                    })?;
+                resolved.collect()
+            }
+            Some(Host::Ipv4(addr)) => {
+                if !self.allowed_domains.is_empty() {
+                    return Err(DownloadError::Domain);
+                }
+                vec![SocketAddr::new(IpAddr::V4(addr), port)]
+            }
+            Some(Host::Ipv6(addr)) => {
+                if !self.allowed_domains.is_empty() {
+                    return Err(DownloadError::Domain);
+                }
+                vec![SocketAddr::new(IpAddr::V6(addr), port)]
+            }
+            None => return Err(DownloadError::Resolve),
+        };
+
+        // a host must not hide private addresses among public ones
+        if let Some(addr) = addrs
+            .iter()
+            .find(|addr| !self.is_allowed_address(&addr.ip()))
+        {
+            return Err(DownloadError::Address(addr.ip()));
+        }
+
+        addrs.first().copied().ok_or(DownloadError::Resolve)
+    }
+
+    /// Requests url while checking the policy for each redirect
+    pub async fn get(&self, mut url: Url) -> Result<Response, DownloadError> {
+        for _ in 0..=self.max_redirects {
+            let addr = self.check(&url).await?;
+
+            let mut builder = reqwest::Client::builder()
+                .redirect(redirect::Policy::none())
+                .connect_timeout(self.connect_timeout)
+                .no_proxy();
+            if let Some(Host::Domain(domain)) = url.host() {
+                // connect to the checked address, not to whatever dns answers next
+                builder = builder.resolve(domain, addr);
+            }
+            let client = builder.build().map_err(DownloadError::Request)?;
+
+            let response = timeout(self.read_timeout, client.get(url.clone()).send())
+                .await
+                .map_err(|_| DownloadError::Timeout)?
+                .map_err(DownloadError::Request)?;
+
+            if !response.status().is_redirection() {
+                return response.error_for_status().map_err(DownloadError::Request);
+            }
+
+            url = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+                .ok_or(DownloadError::Resolve)?;
+        }
+
+        Err(DownloadError::Redirects)
+    }
+
+    /// Streams body of url into dest, aborting as soon as size_limit bytes are exceeded
+    pub async fn download(
+        &self,
+        url: Url,
+        dest: &Path,
+        size_limit: Option<u64>,
+    ) -> Result<u64, DownloadError> {
+        let mut response = self.get(url).await?;
+
+        // header may be missing or lie, but spares the download if it's honest
+        if let (Some(limit), Some(size)) = (size_limit, response.content_length()) {
+            if size > limit {
+                return Err(DownloadError::TooLarge);
+            }
+        }
+
+        let mut file = File::create(dest).await.map_err(DownloadError::Io)?;
+        let mut received = 0u64;
+
+        let result = loop {
+            let chunk = match timeout(self.read_timeout, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => chunk,
+                Ok(Ok(None)) => break file.flush().await.map_err(DownloadError::Io),
+                Ok(Err(why)) => break Err(DownloadError::Request(why)),
+                Err(_) => break Err(DownloadError::Timeout),
+            };
+
+            received += chunk.len() as u64;
+            if size_limit.is_some_and(|limit| received > limit) {
+                break Err(DownloadError::TooLarge);
+            }
+
+            if let Err(why) = file.write_all(&chunk).await {
+                break Err(DownloadError::Io(why));
+            }
+        };
+
+        if result.is_err() {
+            drop(file);
+            if let Err(why) = tokio::fs::remove_file(dest).await {
+                warn!(
+                    "Could not remove partial download {}: {}",
+                    dest.display(),
+                    why
+                );
+            }
+        }
+
+        result.map(|_| received)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempfiles::TempDir;
+    use hyper::{
+        header::HeaderValue,
+        service::{make_service_fn, service_fn},
+        Body, Request, Server, StatusCode,
+    };
+    use std::convert::Infallible;
+
+    const FILE_SIZE: usize = 1000;
+
+    async fn respond(request: Request<Body>) -> Result<hyper::Response<Body>, Infallible> {
+        let path = request.uri().path();
+        let redirect = |location: &str| {
+            let mut response = hyper::Response::new(Body::empty());
+            *response.status_mut() = StatusCode::FOUND;
+            response
+                .headers_mut()
+                .insert(LOCATION, HeaderValue::from_str(location).unwrap());
+            response
+        };
+
+        Ok(match path {
+            "/file" => hyper::Response::new(Body::from(vec![0u8; FILE_SIZE])),
+            // chunked, without a content length
+            "/stream" => {
+                let (mut sender, body) = Body::channel();
+                tokio::spawn(async move {
+                    for _ in 0..FILE_SIZE / 100 {
+                        if sender.send_data(vec![0u8; 100].into()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+                hyper::Response::new(body)
+            }
+            "/scheme" => redirect("ftp://127.0.0.1/file"),
+            _ => match path
+                .strip_prefix("/redirect/")
+                .and_then(|n| n.parse::<u32>().ok())
+            {
+                Some(0) => redirect("/file"),
+                Some(n) => redirect(&format!("/redirect/{}", n - 1)),
+                None => {
+                    let mut response = hyper::Response::new(Body::empty());
+                    *response.status_mut() = StatusCode::NOT_FOUND;
+                    response
+                }
+            },
+        })
+    }
+
+    /// Serves the test routes on a local port
+    fn serve() -> Url {
+        let make_service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(respond)) });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let url = Url::parse(&format!("http://{}/", server.local_addr())).unwrap();
+        tokio::spawn(server);
+        url
+    }
+
+    fn local_policy() -> DownloadPolicy {
+        DownloadPolicy {
+            max_redirects: 2,
+            allow_private_addresses: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn private_v6_addresses() {
+        for addr in [
+            "::1",
+            "::ffff:127.0.0.1",
+            "::10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::1",
+            "2002:c0a8:0101::1",
+            "2001:0::1",
+            "fc00::1",
+            "fe80::1",
+            "fec0::1",
+            "2001:db8::1",
+            "100::1",
+        ] {
+            assert!(
+                !DownloadPolicy::is_public_v6(&addr.parse().unwrap()),
+                "{addr} is public"
+            );
+        }
+        for addr in [
+            "2606:4700:4700::1111",
+            "::ffff:1.1.1.1",
+            "64:ff9b::101:101",
+            "2002:101:101::1",
+        ] {
+            assert!(
+                DownloadPolicy::is_public_v6(&addr.parse().unwrap()),
+                "{addr} is private"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn check() {
+        let policy = DownloadPolicy::default();
+        for url in [
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://169.254.169.254/",
+            "http://100.64.0.1/",
+            "http://[::1]/",
+            "http://[64:ff9b::a9fe:a9fe]/",
+            "http://[2002:a9fe:a9fe::]/",
+            "http://[::a9fe:a9fe]/",
+            "http://[fec0::1]/",
+            "http://[2001:db8::1]/",
+        ] {
+            assert!(
+                matches!(
+                    policy.check(&Url::parse(url).unwrap()).await,
+                    Err(DownloadError::Address(_))
+                ),
+                "{url} is allowed"
+            );
+        }
+
+        assert!(matches!(
+            policy.check(&Url::parse("ftp://1.1.1.1/").unwrap()).await,
+            Err(DownloadError::Scheme)
+        ));
+        assert_eq!(
+            policy
+                .check(&Url::parse("https://1.1.1.1/chime.mp3").unwrap())
+                .await
+                .unwrap(),
+            SocketAddr::from(([1, 1, 1, 1], 443))
+        );
+
+        let policy = DownloadPolicy {
+            allowed_domains: DownloadPolicy::parse_domains("example.com"),
+            denied_domains: DownloadPolicy::parse_domains("bad.example.com"),
+            ..Default::default()
+        };
+        for url in [
+            "http://bad.example.com/",
+            "http://sub.bad.example.com/",
+            "http://example.org/",
+            "http://notexample.com/",
+            "http://1.1.1.1/",
+        ] {
+            assert!(
+                matches!(
+                    policy.check(&Url::parse(url).unwrap()).await,
+                    Err(DownloadError::Domain)
+                ),
+                "{url} is allowed"
+            );
+        }
+
+        assert!(local_policy()
+            .check(&Url::parse("http://127.0.0.1/").unwrap())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn get_follows_redirects() {
+        let url = serve();
+        let policy = local_policy();
+
+        let response = policy.get(url.join("redirect/1").unwrap()).await.unwrap();
+        assert_eq!(response.url().path(), "/file");
+        assert_eq!(response.bytes().await.unwrap().len(), FILE_SIZE);
+
+        assert!(matches!(
+            policy.get(url.join("redirect/2").unwrap()).await,
+            Err(DownloadError::Redirects)
+        ));
+        // each redirect is checked against the policy
+        assert!(matches!(
+            policy.get(url.join("scheme").unwrap()).await,
+            Err(DownloadError::Scheme)
+        ));
+        assert!(matches!(
+            policy.get(url.join("missing").unwrap()).await,
+            Err(DownloadError::Request(_))
+        ));
+        assert!(matches!(
+            DownloadPolicy::default()
+                .get(url.join("file").unwrap())
+                .await,
+            Err(DownloadError::Address(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn download_stops_at_size_limit() {
+        let url = serve();
+        let policy = local_policy();
+        // leftovers of previous runs are removed
+        let dir = TempDir::new(std::env::temp_dir().join("dab-rs-download-test")).unwrap();
+
+        for path in ["file", "stream"] {
+            let dest = dir.file();
+            let received = policy
+                .download(url.join(path).unwrap(), dest.path(), Some(FILE_SIZE as u64))
+                .await
+                .unwrap();
+            assert_eq!(received, FILE_SIZE as u64);
+            assert_eq!(
+                std::fs::metadata(dest.path()).unwrap().len(),
+                FILE_SIZE as u64
+            );
+
+            let dest = dir.file();
+            assert!(matches!(
+                policy
+                    .download(
+                        url.join(path).unwrap(),
+                        dest.path(),
+                        Some(FILE_SIZE as u64 - 1)
+                    )
+                    .await,
+                Err(DownloadError::TooLarge)
+            ));
+            assert!(!dest.path().exists(), "partial download of {path} was kept");
+        }
+    }
+}
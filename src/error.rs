@@ -0,0 +1,220 @@
+use crate::{
+    chimes::ChimeSinkError, download::DownloadError, resolve::ResolveError,
+    validation::AttachmentError,
+};
+
+use fluent_bundle::FluentArgs;
+use log::Level;
+use std::{error::Error, fmt::Display, time::Duration};
+
+/// Failure while handling an interaction, answered with a localized message
+#[derive(Debug)]
+pub enum CommandError {
+    /// options do not match the registered command, which is likely outdated
+    Malformed(String),
+    OnlyInGuilds,
+    MissingPermissions,
+    RateLimited(Duration),
+    BadUrl(url::ParseError),
+    FileTooLarge {
+        /// unknown if the download was aborted early
+        size_bytes: Option<u64>,
+        limit_bytes: u64,
+    },
+    /// attachment could not be fetched from discord
+    Attachment(serenity::Error),
+    Download(DownloadError),
+    Resolve(ResolveError),
+    Validation(AttachmentError),
+    NoChime {
+        user_id: u64,
+    },
+    /// message picked from the context menu has no audio file attached
+    NoAudioAttachment,
+    AlreadyDecided,
+    DirectMessage(serenity::Error),
+    Storage(ChimeSinkError),
+    Io(std::io::Error),
+    Database(tokio_postgres::Error),
+    Discord(serenity::Error),
+}
+impl CommandError {
+    /// Level to log this at, errors are failures of the bot rather than of the user
+    pub fn log_level(&self) -> Level {
+        match self {
+            CommandError::OnlyInGuilds
+            | CommandError::RateLimited(_)
+            | CommandError::BadUrl(_)
+            | CommandError::FileTooLarge { .. }
+            | CommandError::NoChime { .. }
+            | CommandError::NoAudioAttachment
+            | CommandError::AlreadyDecided => Level::Info,
+            CommandError::Download(why) => match why {
+                DownloadError::Resolve
+                | DownloadError::Request(_)
+                | DownloadError::TooLarge
+                | DownloadError::Timeout => Level::Info,
+                DownloadError::Io(_) => Level::Error,
+                _ => Level::Warn,
+            },
+            CommandError::Resolve(why) => match why {
+                ResolveError::Io(_) => Level::Error,
+                _ => Level::Warn,
+            },
+            CommandError::Validation(why) => match why {
+                AttachmentError::Tempfile => Level::Error,
+                _ => Level::Info,
+            },
+            CommandError::Malformed(_)
+            | CommandError::MissingPermissions
+            | CommandError::Attachment(_)
+            | CommandError::DirectMessage(_) => Level::Warn,
+            CommandError::Storage(_)
+            | CommandError::Io(_)
+            | CommandError::Database(_)
+            | CommandError::Discord(_) => Level::Error,
+        }
+    }
+
+    /// Id of the localized message describing this error
+    pub fn message_id(&self) -> &'static str {
+        match self {
+            CommandError::Malformed(_) => "command-outdated",
+            CommandError::OnlyInGuilds => "only-in-guilds",
+            CommandError::MissingPermissions => "missing-permissions",
+            CommandError::RateLimited(_) => "rate-limited",
+            CommandError::BadUrl(_) => "bad-url",
+            CommandError::FileTooLarge { .. } => "file-too-large",
+            CommandError::Attachment(_) => "download-failed",
+            CommandError::Download(why) => match why {
+                DownloadError::Scheme
+                | DownloadError::Domain
+                | DownloadError::Address(_)
+                | DownloadError::Redirects => "url-forbidden",
+                DownloadError::Resolve | DownloadError::Request(_) => "bad-url",
+                // too large downloads are reported along with the limit as FileTooLarge
+                DownloadError::TooLarge | DownloadError::Timeout | DownloadError::Io(_) => {
+                    "download-failed"
+                }
+            },
+            CommandError::Resolve(_) => "resolve-failed",
+            CommandError::Validation(why) => why.message_id(),
+            CommandError::NoChime { .. } => "user-has-no-chime",
+            CommandError::NoAudioAttachment => "no-audio-attachment",
+            CommandError::AlreadyDecided => "moderation-already-decided",
+            CommandError::DirectMessage(_) => "privacy-export-dm-failed",
+            CommandError::Storage(_)
+            | CommandError::Io(_)
+            | CommandError::Database(_)
+            | CommandError::Discord(_) => "internal-error",
+        }
+    }
+
+    pub fn args(&self) -> Option<FluentArgs<'static>> {
+        // seconds with a single decimal, e.g. 3.5
+        let seconds = |duration: &Duration| (duration.as_secs_f64() * 10.0).round() / 10.0;
+        let kilobytes = |bytes: &u64| bytes.div_ceil(1000);
+
+        let mut args = FluentArgs::new();
+        match self {
+            CommandError::RateLimited(retry_after) => {
+                let retry_at = chrono::Utc::now().timestamp() + retry_after.as_secs() as i64 + 1;
+                args.set("retry", format!("<t:{retry_at}:R>"));
+            }
+            CommandError::FileTooLarge {
+                size_bytes,
+                limit_bytes,
+            } => {
+                match size_bytes {
+                    Some(size) => args.set("size", kilobytes(size)),
+                    None => args.set("size", "unknown"),
+                }
+                args.set("limit", kilobytes(limit_bytes));
+            }
+            CommandError::Validation(why) => match why {
+                AttachmentError::Duration { duration, limit } => {
+                    args.set("duration", seconds(duration));
+                    args.set("limit", seconds(limit));
+                }
+                AttachmentError::Codec { codec, allowed } => {
+                    args.set("codec", codec.clone());
+                    args.set("allowed", allowed.join(", "));
+                }
+                AttachmentError::Channels { channels, max } => {
+                    match channels {
+                        Some(channels) => args.set("channels", *channels),
+                        None => args.set("channels", "unknown"),
+                    }
+                    args.set("max", *max);
+                }
+                AttachmentError::SampleRate { rate, allowed } => {
+                    match rate {
+                        Some(rate) => args.set("rate", *rate),
+                        None => args.set("rate", "unknown"),
+                    }
+                    args.set("min", *allowed.start());
+                    args.set("max", *allowed.end());
+                }
+                _ => return None,
+            },
+            CommandError::NoChime { user_id } => args.set("user", format!("<@{user_id}>")),
+            _ => return None,
+        }
+        Some(args)
+    }
+}
+impl Error for CommandError {}
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Malformed(what) => write!(f, "malformed command: {what}"),
+            CommandError::OnlyInGuilds => write!(f, "only available in guilds"),
+            CommandError::MissingPermissions => write!(f, "missing permissions"),
+            CommandError::RateLimited(retry_after) => {
+                write!(f, "rate limited for {}s", retry_after.as_secs())
+            }
+            CommandError::BadUrl(why) => write!(f, "bad url: {why}"),
+            CommandError::FileTooLarge {
+                size_bytes,
+                limit_bytes,
+            } => write!(f, "file too large: {size_bytes:?} > {limit_bytes} bytes"),
+            CommandError::Attachment(why) => write!(f, "attachment download failed: {why}"),
+            CommandError::Download(why) => write!(f, "download failed: {why}"),
+            CommandError::Resolve(why) => write!(f, "resolving failed: {why}"),
+            CommandError::Validation(why) => write!(f, "invalid chime: {why}"),
+            CommandError::NoChime { user_id } => write!(f, "user {user_id} has no chime"),
+            CommandError::NoAudioAttachment => write!(f, "message has no audio attachment"),
+            CommandError::AlreadyDecided => write!(f, "review was decided already"),
+            CommandError::DirectMessage(why) => write!(f, "could not send direct message: {why}"),
+            CommandError::Storage(why) => write!(f, "chime storage failed: {why:?}"),
+            CommandError::Io(why) => write!(f, "io failed: {why}"),
+            CommandError::Database(why) => write!(f, "database failed: {why}"),
+            CommandError::Discord(why) => write!(f, "discord request failed: {why}"),
+        }
+    }
+}
+impl From<AttachmentError> for CommandError {
+    fn from(why: AttachmentError) -> Self {
+        CommandError::Validation(why)
+    }
+}
+impl From<ChimeSinkError> for CommandError {
+    fn from(why: ChimeSinkError) -> Self {
+        CommandError::Storage(why)
+    }
+}
+impl From<std::io::Error> for CommandError {
+    fn from(why: std::io::Error) -> Self {
+        CommandError::Io(why)
+    }
+}
+impl From<tokio_postgres::Error> for CommandError {
+    fn from(why: tokio_postgres::Error) -> Self {
+        CommandError::Database(why)
+    }
+}
+impl From<serenity::Error> for CommandError {
+    fn from(why: serenity::Error) -> Self {
+        CommandError::Discord(why)
+    }
+}
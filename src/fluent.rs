@@ -1,12 +1,141 @@
 use fluent_bundle::{bundle::FluentBundle, FluentArgs, FluentResource};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use fluent_syntax::ast;
 use intl_memoizer::concurrent::IntlLangMemoizer;
-use log::{error, warn};
-use std::{borrow::Cow, collections::HashMap, error::Error, fs, path::PathBuf};
+use log::{error, info, warn};
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt::Display,
+    fs,
+    path::PathBuf,
+};
 use unic_langid::LanguageIdentifier;
 
+type Bundle = FluentBundle<FluentResource, IntlLangMemoizer>;
+
+/// Differences between the localizations and the messages used by the code
+#[derive(Debug, Default)]
+pub struct LocalizationReport {
+    /// locale and id of messages used by the code or the fallback, but not localized
+    pub missing: Vec<(String, String)>,
+    /// locale and id of messages neither used by the code nor by other messages
+    pub extra: Vec<(String, String)>,
+    /// locale, id and variables of messages whose variables are not provided by the code
+    /// or differ from the fallback
+    pub mismatched: Vec<(String, String, Vec<String>)>,
+    /// locale, id and value of `.name` attributes that discord would reject as command names
+    pub invalid_names: Vec<(String, String, String)>,
+}
+impl LocalizationReport {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.mismatched.is_empty()
+            && self.invalid_names.is_empty()
+    }
+}
+impl Display for LocalizationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (locale, id) in &self.missing {
+            writeln!(f, "{locale}: missing '{id}'")?;
+        }
+        for (locale, id) in &self.extra {
+            writeln!(f, "{locale}: unused '{id}'")?;
+        }
+        for (locale, id, variables) in &self.mismatched {
+            writeln!(f, "{locale}: unexpected variables {variables:?} in '{id}'")?;
+        }
+        for (locale, id, name) in &self.invalid_names {
+            writeln!(f, "{locale}: invalid command name '{name}' in '{id}'")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether discord accepts name for commands and options: 1-32 lowercase letters, digits,
+/// dashes or underscores
+fn is_valid_command_name(name: &str) -> bool {
+    (1..=32).contains(&name.chars().count())
+        && name
+            .chars()
+            .all(|c| (c.is_alphanumeric() || c == '-' || c == '_') && !c.is_uppercase())
+}
+
+/// Collects variables used by pattern and ids of messages it references, including theirs
+fn collect_variables(
+    bundle: &Bundle,
+    pattern: &ast::Pattern<&str>,
+    variables: &mut BTreeSet<String>,
+    references: &mut BTreeSet<String>,
+) {
+    fn inline(
+        bundle: &Bundle,
+        expression: &ast::InlineExpression<&str>,
+        variables: &mut BTreeSet<String>,
+        references: &mut BTreeSet<String>,
+    ) {
+        match expression {
+            ast::InlineExpression::VariableReference { id } => {
+                variables.insert(id.name.to_string());
+            }
+            // references are followed once, which also ends cycles
+            ast::InlineExpression::MessageReference { id, .. }
+                if references.insert(id.name.to_string()) =>
+            {
+                if let Some(pattern) = bundle.get_message(id.name).and_then(|m| m.value()) {
+                    collect_variables(bundle, pattern, variables, references);
+                }
+            }
+            ast::InlineExpression::FunctionReference { arguments, .. } => {
+                for argument in &arguments.positional {
+                    inline(bundle, argument, variables, references);
+                }
+                for argument in &arguments.named {
+                    inline(bundle, &argument.value, variables, references);
+                }
+            }
+            ast::InlineExpression::Placeable { expression } => {
+                placeable(bundle, expression, variables, references)
+            }
+            _ => {}
+        }
+    }
+
+    fn placeable(
+        bundle: &Bundle,
+        expression: &ast::Expression<&str>,
+        variables: &mut BTreeSet<String>,
+        references: &mut BTreeSet<String>,
+    ) {
+        match expression {
+            ast::Expression::Select { selector, variants } => {
+                inline(bundle, selector, variables, references);
+                for variant in variants {
+                    collect_variables(bundle, &variant.value, variables, references);
+                }
+            }
+            ast::Expression::Inline(expression) => {
+                inline(bundle, expression, variables, references)
+            }
+        }
+    }
+
+    for element in &pattern.elements {
+        if let ast::PatternElement::Placeable { expression } = element {
+            placeable(bundle, expression, variables, references);
+        }
+    }
+}
+
 pub struct FluentLocalizer {
     pub fallback_locale: LanguageIdentifier,
-    resources: HashMap<LanguageIdentifier, FluentBundle<FluentResource, IntlLangMemoizer>>,
+    resources: HashMap<LanguageIdentifier, Bundle>,
+    /// keys of resources, sorted to negotiate deterministically
+    locales: Vec<LanguageIdentifier>,
+    /// ids of the messages in each bundle
+    message_ids: HashMap<LanguageIdentifier, BTreeSet<String>>,
 }
 impl FluentLocalizer {
     pub fn new(
@@ -15,6 +144,7 @@ impl FluentLocalizer {
     ) -> Result<Self, Box<dyn Error>> {
         let res_dir = fs::read_dir(resource_dir)?;
         let mut map = HashMap::new();
+        let mut message_ids = HashMap::new();
 
         for entry in res_dir.flatten() {
             if !entry.path().is_dir() {
@@ -26,6 +156,7 @@ impl FluentLocalizer {
                 .to_string_lossy()
                 .parse::<LanguageIdentifier>()?;
             let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+            let mut ids = BTreeSet::new();
 
             for resource in (entry.path().read_dir()?).flatten() {
                 if !resource.path().is_file()
@@ -42,6 +173,11 @@ impl FluentLocalizer {
                 let resource_parsed = FluentResource::try_new(resource_string);
 
                 if let Ok(resource_parsed) = resource_parsed {
+                    for entry in resource_parsed.entries() {
+                        if let ast::Entry::Message(message) = entry {
+                            ids.insert(message.id.name.to_string());
+                        }
+                    }
                     if let Err(why) = bundle.add_resource(resource_parsed) {
                         error!("Could not add resource: {why:?}");
                     }
@@ -54,6 +190,7 @@ impl FluentLocalizer {
                 }
             }
 
+            message_ids.insert(locale.clone(), ids);
             map.insert(locale, bundle);
         }
 
@@ -61,12 +198,94 @@ impl FluentLocalizer {
             panic!("Fallback locale is not provided!");
         }
 
+        let mut locales: Vec<_> = map.keys().cloned().collect();
+        locales.sort_by_key(|locale| locale.to_string());
+
         Ok(FluentLocalizer {
             fallback_locale,
             resources: map,
+            locales,
+            message_ids,
         })
     }
 
+    /// Cross-checks all localizations against the messages used by the code, given as
+    /// ids and the variables passed to them, and against the fallback
+    pub fn check(&self, used: &[(&str, &[&str])]) -> LocalizationReport {
+        let mut report = LocalizationReport::default();
+
+        let is_used = |id: &str| used.iter().any(|(used_id, _)| *used_id == id);
+        let variables_of = |bundle: &Bundle, id: &str, references: &mut BTreeSet<String>| {
+            let mut variables = BTreeSet::new();
+            if let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) {
+                collect_variables(bundle, pattern, &mut variables, references);
+            }
+            variables
+        };
+
+        let fallback_bundle = &self.resources[&self.fallback_locale];
+        let fallback_ids = &self.message_ids[&self.fallback_locale];
+
+        for locale in &self.locales {
+            let bundle = &self.resources[locale];
+            let ids = &self.message_ids[locale];
+
+            for (id, _) in used {
+                if !ids.contains(*id) {
+                    report.missing.push((locale.to_string(), id.to_string()));
+                }
+            }
+            for id in fallback_ids.difference(ids) {
+                if !is_used(id) {
+                    report.missing.push((locale.to_string(), id.clone()));
+                }
+            }
+
+            let mut references = BTreeSet::new();
+            for id in ids {
+                let mut id_references = BTreeSet::new();
+                let variables = variables_of(bundle, id, &mut id_references);
+                references.append(&mut id_references);
+
+                let unprovided =
+                    used.iter()
+                        .find(|(used_id, _)| used_id == id)
+                        .is_some_and(|(_, provided)| {
+                            variables.iter().any(|v| !provided.contains(&v.as_str()))
+                        });
+                let differing = locale != &self.fallback_locale
+                    && fallback_ids.contains(id)
+                    && variables != variables_of(fallback_bundle, id, &mut BTreeSet::new());
+
+                if unprovided || differing {
+                    report.mismatched.push((
+                        locale.to_string(),
+                        id.clone(),
+                        variables.into_iter().collect(),
+                    ));
+                }
+            }
+
+            for id in ids {
+                if !references.contains(id) && !is_used(id) {
+                    report.extra.push((locale.to_string(), id.clone()));
+                }
+            }
+
+            for id in ids {
+                if let Some(name) = self.localize_attribute(&locale.to_string(), id, "name") {
+                    if !is_valid_command_name(&name) {
+                        report
+                            .invalid_names
+                            .push((locale.to_string(), id.clone(), name));
+                    }
+                }
+            }
+        }
+
+        report
+    }
+
     pub fn get_available_localizations(&self) -> Vec<String> {
         let mut localizations = vec![];
 
@@ -77,20 +296,38 @@ impl FluentLocalizer {
         localizations
     }
 
-    pub fn get_bundle(&self, lang_id: &str) -> &FluentBundle<FluentResource, IntlLangMemoizer> {
-        let mut lang_id = match lang_id.parse::<LanguageIdentifier>() {
-            Ok(id) => id,
+    /// Available locales matching lang_id, best match first, e.g. `en-US` for `en-GB`
+    fn negotiate(&self, lang_id: &str) -> Vec<&LanguageIdentifier> {
+        match lang_id.parse::<LanguageIdentifier>() {
+            Ok(id) => {
+                negotiate_languages(&[id], &self.locales, None, NegotiationStrategy::Filtering)
+            }
             Err(why) => {
-                error!("Invalid lang_id: '{lang_id}': {why} - Using fallback...");
-                self.fallback_locale.clone()
+                warn!("Invalid lang_id: '{lang_id}': {why}");
+                vec![]
             }
-        };
+        }
+    }
 
-        if !self.resources.contains_key(&lang_id) {
-            error!("No localization for '{lang_id}' available! - Using fallback...");
-            lang_id = self.fallback_locale.clone();
+    /// Whether messages can be localized to lang_id without falling back
+    pub fn is_available(&self, lang_id: &str) -> bool {
+        !self.negotiate(lang_id).is_empty()
+    }
+
+    /// Bundles to look up messages for lang_id in, ending with the fallback
+    fn get_bundles(&self, lang_id: &str) -> Vec<(&LanguageIdentifier, &Bundle)> {
+        let mut chain = self.negotiate(lang_id);
+        if chain.is_empty() {
+            info!("No localization for '{lang_id}' available! - Using fallback...");
+        }
+        if !chain.contains(&&self.fallback_locale) {
+            chain.push(&self.fallback_locale);
         }
-        self.resources.get(&lang_id).unwrap()
+
+        chain
+            .into_iter()
+            .map(|locale| (locale, &self.resources[locale]))
+            .collect()
     }
 
     pub fn localize<'r>(
@@ -98,20 +335,29 @@ impl FluentLocalizer {
         lang_id: &str,
         msg: &str,
         args: Option<&'r FluentArgs>,
-    ) -> Cow<str> {
-        // TODO this also returns "not translated" when set of translated msgs for each initially available locale are not equal to each other
-        let bundle = self.get_bundle(lang_id);
-
-        let msg = match bundle.get_message(msg) {
-            Some(fluent_message) => match fluent_message.value() {
-                Some(pattern) => pattern,
-                None => {
-                    error!("Translation for '{msg}' in lang '{lang_id}' has no pattern!");
-                    return Cow::Borrowed("Not translated :b");
+    ) -> Cow<'r, str> {
+        // messages missing in a locale are taken from the next one in its chain
+        let found = self
+            .get_bundles(lang_id)
+            .into_iter()
+            .find_map(|(locale, bundle)| {
+                match bundle.get_message(msg).map(|message| message.value()) {
+                    Some(Some(pattern)) => Some((bundle, pattern)),
+                    Some(None) => {
+                        error!("Translation for '{msg}' in lang '{locale}' has no pattern!");
+                        None
+                    }
+                    None => {
+                        warn!("Translation for '{msg}' in lang '{locale}' not available!");
+                        None
+                    }
                 }
-            },
+            });
+
+        let (bundle, msg) = match found {
+            Some(found) => found,
             None => {
-                error!("Translation for '{msg}' in lang '{lang_id}' not available!");
+                error!("Translation for '{msg}' not available in any lang for '{lang_id}'!");
                 return Cow::Borrowed("Not translated :b");
             }
         };
@@ -125,4 +371,21 @@ impl FluentLocalizer {
 
         retval
     }
+
+    /// Attribute of msg in exactly lang_id, without falling back to other locales,
+    /// e.g. the `.name` translating a command name
+    pub fn localize_attribute(&self, lang_id: &str, msg: &str, attribute: &str) -> Option<String> {
+        let locale = lang_id.parse::<LanguageIdentifier>().ok()?;
+        let bundle = self.resources.get(&locale)?;
+        let pattern = bundle.get_message(msg)?.get_attribute(attribute)?.value();
+
+        let mut errors = vec![];
+        let retval = bundle.format_pattern(pattern, None, &mut errors);
+
+        if !errors.is_empty() {
+            warn!("Errors while formatting: {msg}.{attribute}: {errors:?}");
+        }
+
+        Some(retval.into_owned())
+    }
 }
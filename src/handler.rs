@@ -1,57 +1,67 @@
-use crate::*;
+use crate::{
+    chimes::{ChimeSink, ChimeSinkError},
+    data::ReviewStatus,
+    error::CommandError,
+    moderation::Approval,
+    response::Response,
+    *,
+};
+
+use fluent_bundle::FluentArgs;
 
-use ffprobe::ffprobe;
-use log::{error, info, warn};
+use log::{error, info, log, warn};
 use serenity::{
     async_trait,
+    builder::CreateApplicationCommandOption,
     model::{
-        application::interaction::{
-            application_command::{
-                ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+        application::{
+            command::CommandOptionType,
+            component::ButtonStyle,
+            interaction::{
+                application_command::{
+                    ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+                },
+                autocomplete::AutocompleteInteraction,
+                message_component::MessageComponentInteraction,
+                Interaction, InteractionResponseType,
             },
-            Interaction, InteractionResponseType,
         },
+        channel::{Attachment, AttachmentType, ChannelType, Message},
         gateway::Ready,
-        id::GuildId,
+        id::{ChannelId, GuildId},
     },
     prelude::*,
 };
-use std::{
-    env::temp_dir, error::Error, fmt::Display, fs::File, os::unix::prelude::FileExt, sync::Arc,
-    time::Duration,
-};
+use std::{path::Path, sync::Arc, time::Duration};
 use tokio::{
     sync::Mutex,
     task::{self, JoinHandle},
 };
 
-#[derive(Debug)]
-enum AttachmentError {
-    Duration,
-    Unreadable,
-    Tempfile,
-}
-impl Error for AttachmentError {}
-impl Display for AttachmentError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            // use localization strings here
-            AttachmentError::Duration => write!(f, "duration-exceeded"),
-            AttachmentError::Unreadable => write!(f, "data-unreadable"),
-            AttachmentError::Tempfile => write!(f, "internal-error"),
-        }
-    }
-}
+const PRIVACY_DELETE_CONFIRM_ID: &str = "privacy-delete-confirm";
+const MODERATION_APPROVE_PREFIX: &str = "moderation-approve:";
+const MODERATION_REJECT_PREFIX: &str = "moderation-reject:";
+/// discord shows at most this many suggestions, each at most this long
+const AUTOCOMPLETE_CHOICES: usize = 25;
+const AUTOCOMPLETE_CHOICE_LENGTH: usize = 100;
+
 #[derive(Default)]
 pub struct HandlerBuilder {
     sink: Option<Arc<dyn chimes::ChimeSink>>,
     bus_size: Option<usize>,
     file_size_limit_bytes: Option<isize>,
-    file_duration_max: Option<Duration>,
+    validation_policy: Option<validation::ValidationPolicy>,
     command_root: Option<String>,
     disconnect_timeout: Option<Duration>,
     localizer: Option<fluent::FluentLocalizer>,
-    database: Option<data::DatabaseInterface>,
+    database: Option<Arc<data::DatabaseInterface>>,
+    health: Option<Arc<health::HealthState>>,
+    download_policy: Option<download::DownloadPolicy>,
+    resolvers: Vec<Box<dyn resolve::MediaResolver>>,
+    temp_dir: Option<tempfiles::TempDir>,
+    cooldown_limits: Option<cooldown::CooldownLimits>,
+    rate_limiter: Option<ratelimit::CommandRateLimiter>,
+    command_scope: Option<registration::CommandScope>,
 }
 impl HandlerBuilder {
     pub fn sink<T>(mut self, sink: Arc<T>) -> HandlerBuilder
@@ -77,12 +87,44 @@ impl HandlerBuilder {
         self.localizer = Some(localizer);
         self
     }
-    pub fn database(mut self, database: data::DatabaseInterface) -> HandlerBuilder {
+    pub fn database(mut self, database: Arc<data::DatabaseInterface>) -> HandlerBuilder {
         self.database = Some(database);
         self
     }
-    pub fn file_duration_max(mut self, duration: Duration) -> HandlerBuilder {
-        self.file_duration_max = Some(duration);
+    pub fn health(mut self, health: Arc<health::HealthState>) -> HandlerBuilder {
+        self.health = Some(health);
+        self
+    }
+    pub fn download_policy(mut self, policy: download::DownloadPolicy) -> HandlerBuilder {
+        self.download_policy = Some(policy);
+        self
+    }
+    #[cfg_attr(not(feature = "yt-dlp"), allow(dead_code))]
+    pub fn resolver<T>(mut self, resolver: T) -> HandlerBuilder
+    where
+        T: resolve::MediaResolver + 'static,
+    {
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+    pub fn temp_dir(mut self, dir: tempfiles::TempDir) -> HandlerBuilder {
+        self.temp_dir = Some(dir);
+        self
+    }
+    pub fn cooldown_limits(mut self, limits: cooldown::CooldownLimits) -> HandlerBuilder {
+        self.cooldown_limits = Some(limits);
+        self
+    }
+    pub fn rate_limiter(mut self, limiter: ratelimit::CommandRateLimiter) -> HandlerBuilder {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+    pub fn command_scope(mut self, scope: registration::CommandScope) -> HandlerBuilder {
+        self.command_scope = Some(scope);
+        self
+    }
+    pub fn validation_policy(mut self, policy: validation::ValidationPolicy) -> HandlerBuilder {
+        self.validation_policy = Some(policy);
         self
     }
     pub fn file_size_limit(mut self, bytes: isize) -> HandlerBuilder {
@@ -90,23 +132,36 @@ impl HandlerBuilder {
         self
     }
     pub fn build(self) -> Handler {
+        let database = self.database.expect("Expected database");
+
         Handler {
             file_size_limit_bytes: self.file_size_limit_bytes.expect("Expected filesize limit"),
-            command_root: self.command_root.expect("Expected command root"),
+            commands: command_registry(&self.command_root.expect("Expected command root")),
             disconnect_timeout: self
                 .disconnect_timeout
                 .expect("Expected disconnect timeout"),
-            file_duration_max: self
-                .file_duration_max
-                .expect("Expected maximum file duration"),
-            sink: self.sink.expect("Expected chime sink"),
+            validation_policy: self.validation_policy.expect("Expected validation policy"),
+            sink: Arc::new(moderation::ModeratedChimeSink::new(
+                self.sink.expect("Expected chime sink"),
+                Arc::clone(&database),
+            )),
             watchers: Mutex::new(HashMap::new()),
             cleanup_watcher: Mutex::new(None),
             flag_map: Arc::new(Mutex::new(HashMap::new())),
             bus: Mutex::new(bus::Bus::new(self.bus_size.expect("Expected bus size"))),
             latest_context: Arc::new(Mutex::new(None)),
             localizer: Mutex::new(self.localizer.expect("Expected localizer")),
-            database: self.database.expect("Expected database"),
+            database,
+            health: self.health.unwrap_or_default(),
+            download_policy: self.download_policy.unwrap_or_default(),
+            resolvers: self.resolvers,
+            temp_dir: self.temp_dir.expect("Expected temporary directory"),
+            cooldowns: cooldown::Cooldowns::default(),
+            cooldown_limits: self.cooldown_limits.unwrap_or_default(),
+            rate_limiter: self.rate_limiter.unwrap_or_default(),
+            command_scope: self
+                .command_scope
+                .unwrap_or(registration::CommandScope::Global),
         }
     }
 }
@@ -120,11 +175,11 @@ struct BusChimePayload {
 }
 pub struct Handler {
     file_size_limit_bytes: isize,
-    command_root: String,
+    commands: commands::CommandRegistry<Handler>,
     disconnect_timeout: Duration,
-    file_duration_max: Duration,
+    validation_policy: validation::ValidationPolicy,
 
-    sink: Arc<dyn chimes::ChimeSink>,
+    sink: Arc<moderation::ModeratedChimeSink>,
     watchers: Mutex<HashMap<u64, JoinHandle<()>>>,
     cleanup_watcher: Mutex<Option<JoinHandle<()>>>,
     flag_map: Arc<Mutex<HashMap<u64, bool>>>,
@@ -134,18 +189,32 @@ pub struct Handler {
 
     localizer: Mutex<fluent::FluentLocalizer>,
 
-    database: data::DatabaseInterface,
+    database: Arc<data::DatabaseInterface>,
+
+    health: Arc<health::HealthState>,
+
+    download_policy: download::DownloadPolicy,
+    resolvers: Vec<Box<dyn resolve::MediaResolver>>,
+
+    temp_dir: tempfiles::TempDir,
+
+    cooldowns: cooldown::Cooldowns,
+    cooldown_limits: cooldown::CooldownLimits,
+    rate_limiter: ratelimit::CommandRateLimiter,
+    command_scope: registration::CommandScope,
 }
 impl Handler {
     async fn spawn_cleanup_watcher(&self) -> JoinHandle<()> {
         let timeout = self.disconnect_timeout;
         let flags = Arc::clone(&self.flag_map);
         let ctx = Arc::clone(&self.latest_context);
+        let health = Arc::clone(&self.health);
 
         task::spawn(async move {
             let mut context = Option::<Context>::None;
 
             loop {
+                health.cleanup_watcher_beat().await;
                 tokio::time::sleep(timeout).await;
 
                 if let Some(ctx_buf) = ctx.lock().await.take() {
@@ -203,7 +272,7 @@ impl Handler {
 
                 flags.lock().await.insert(guild_id.0, true);
 
-                if let Ok(chime) = sink_arc.get_input(msg.user_id).await {
+                if let Ok(chime) = sink_arc.get_input(msg.user_id, guild_id.0).await {
                     // dont keep mutex-guards for too long
                     if let Err(why) = call.lock().await.deafen(true).await {
                         error!("Could not deafen: {:?}", why);
@@ -222,85 +291,179 @@ impl Handler {
         })
     }
 
-    async fn process_chime_data(
+    /// Downloads attachment from discord and sets it as chime of user, if it is small enough
+    async fn process_chime_attachment(
         &self,
-        data: &[u8],
+        attachment: &Attachment,
         user_id: u64,
-        filename: Option<&str>,
-    ) -> Result<(), AttachmentError> {
-        let mut temp_path = temp_dir();
-        match filename {
-            Some(name) => temp_path.push(name),
-            None => temp_path.push(uuid::Uuid::new_v4().to_string()),
-        };
-
-        let temp_file = File::create(&temp_path);
-        if temp_file.is_err() {
-            error!("Could not create temporary file {:?}", temp_file);
-            return Err(AttachmentError::Tempfile);
-        }
-        let temp_file = temp_file.unwrap();
-        if let Err(why) = temp_file.write_all_at(data, 0) {
-            error!("Could not write data to file: {:?}", why);
-            return Err(AttachmentError::Tempfile);
+    ) -> Result<(), CommandError> {
+        if self.file_size_limit_bytes >= 0 && attachment.size as isize > self.file_size_limit_bytes
+        {
+            return Err(CommandError::FileTooLarge {
+                size_bytes: Some(attachment.size),
+                limit_bytes: self.file_size_limit_bytes as u64,
+            });
         }
 
-        match ffprobe(&temp_path) {
-            Ok(info) => {
-                let duration = info.format.get_duration(); // seconds
-                if duration.is_none() {
-                    return Err(AttachmentError::Unreadable);
-                }
+        let data = attachment
+            .download()
+            .await
+            .map_err(CommandError::Attachment)?;
 
-                let duration = duration.unwrap();
-                if duration > self.file_duration_max {
-                    return Err(AttachmentError::Duration);
-                }
-            }
-            Err(why) => {
-                error!("FFProbe on data failed: {:?}", why);
-                return Err(AttachmentError::Unreadable);
-            }
+        self.process_chime_data(&data, user_id).await
+    }
+
+    async fn process_chime_data(&self, data: &[u8], user_id: u64) -> Result<(), CommandError> {
+        let temp_file = self.temp_dir.file();
+        std::fs::write(temp_file.path(), data)?;
+
+        self.process_chime_file(temp_file.path(), user_id).await
+    }
+
+    /// Validates file and hands it to the sink, the caller is left to remove it on failure
+    async fn process_chime_file(&self, path: &Path, user_id: u64) -> Result<(), CommandError> {
+        validation::prepare_chime(path, &self.validation_policy).await?;
+        self.sink.save_data(user_id, path.to_path_buf()).await?;
+        Ok(())
+    }
+
+    /// Fetches url into dest, through a resolver if one handles it
+    async fn fetch_url(
+        &self,
+        url: url::Url,
+        dest: &Path,
+        size_limit: Option<u64>,
+    ) -> Result<(), CommandError> {
+        fetch_url(
+            &self.resolvers,
+            &self.download_policy,
+            self.validation_policy.duration_max,
+            url,
+            dest,
+            size_limit,
+        )
+        .await
+    }
+
+    /// Response to a changed chime, which needs approval first if the guild moderates chimes
+    async fn chime_set_response(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Response {
+        let guild_id = match command.guild_id {
+            Some(guild_id) => guild_id.0,
+            None => return Response::Success,
         };
+        let user_id = command.user.id.0;
 
-        match self.sink.save_data(user_id, temp_path).await {
-            Ok(_) => Ok(()),
-            Err(why) => {
-                error!("Could not save chime to sink: {:?}", why);
-                Err(AttachmentError::Tempfile)
+        // the same content keeps the decision that was made about it before
+        match self.sink.approval(guild_id, user_id).await {
+            Approval::Unsubmitted => {
+                self.submit_for_review(ctx, guild_id, user_id).await;
+                Response::ChimePendingApproval
             }
+            Approval::Pending => Response::ChimeStillPending,
+            Approval::Rejected => Response::ChimeRejected,
+            Approval::NotRequired | Approval::Approved => Response::Success,
         }
     }
 
     async fn respond(
         &self,
         command: &ApplicationCommandInteraction,
-        ctx: Context,
-        success: bool,
-        info: Option<&str>,
+        ctx: &Context,
+        response: Response,
     ) {
-        let msg = match info {
-            Some(text) => text,
-            None => {
-                if success {
-                    "success"
-                } else {
-                    "fail"
-                }
-            }
-        };
+        let locale = self.command_locale(command).await;
+        let msg = self.localize_response(&locale, &response).await;
+        self.respond_content(command, ctx, msg).await
+    }
 
-        let msg = self
-            .localizer
+    async fn localize_response(&self, locale: &str, response: &Response) -> String {
+        let args = response.args();
+        self.localizer
             .lock()
             .await
-            .localize(&command.locale, msg, None)
-            .into_owned();
+            .localize(locale, response.message_id(), args.as_ref())
+            .into_owned()
+    }
+
+    /// Locale of text for user in guild: their own choice, then the one of the guild,
+    /// then the one of the interaction, then the fallback
+    async fn resolve_locale(
+        &self,
+        user_id: Option<u64>,
+        guild_id: Option<u64>,
+        interaction_locale: Option<&str>,
+    ) -> String {
+        let user_locale = match user_id {
+            Some(user_id) => self
+                .database
+                .get_user_details(user_id)
+                .await
+                .and_then(|details| details.locale),
+            None => None,
+        };
+        let guild_locale = match guild_id {
+            Some(guild_id) => self
+                .database
+                .get_guild_details(&guild_id)
+                .await
+                .and_then(|details| details.locale),
+            None => None,
+        };
+
+        let localizer = self.localizer.lock().await;
+        [
+            user_locale,
+            guild_locale,
+            interaction_locale.map(str::to_string),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|locale| localizer.is_available(locale))
+        .unwrap_or_else(|| localizer.fallback_locale.to_string())
+    }
+
+    async fn command_locale(&self, command: &ApplicationCommandInteraction) -> String {
+        self.resolve_locale(
+            Some(command.user.id.0),
+            command.guild_id.map(|id| id.0),
+            Some(&command.locale),
+        )
+        .await
+    }
+
+    async fn language_response(&self, locale: Option<String>) -> Response {
+        match locale {
+            Some(locale) => Response::LanguageSet {
+                language: self
+                    .localizer
+                    .lock()
+                    .await
+                    .localize(&locale, "locale-name", None)
+                    .into_owned(),
+            },
+            None => Response::LanguageReset,
+        }
+    }
+
+    async fn respond_content(
+        &self,
+        command: &ApplicationCommandInteraction,
+        ctx: &Context,
+        content: String,
+    ) {
         if let Err(why) = command
             .create_interaction_response(&ctx.http, |response| {
                 response
                     .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|data| data.content(msg).ephemeral(true))
+                    .interaction_response_data(|data| {
+                        data.content(content)
+                            .ephemeral(true)
+                            .allowed_mentions(|mentions| mentions.empty_parse())
+                    })
             })
             .await
         {
@@ -308,242 +471,1668 @@ impl Handler {
         }
     }
 
-    // TODO move this out of impl Handler
-    fn localize<'a, T>(
+    fn format_audit_entry(
         localizer: &fluent::FluentLocalizer,
-        available_locales: &[String],
-        cmd: &'a mut T,
-        msg: &str,
-        name: Option<&str>,
-    ) -> &'a mut T
-    where
-        T: localizable::Localizable + nameable::Nameable,
-    {
-        let default_locale = localizer.fallback_locale.to_string();
+        locale: &str,
+        entry: &data::AuditEntry,
+    ) -> String {
+        let mut args = FluentArgs::new();
+        args.set("time", format!("<t:{}:f>", entry.created_at));
+        args.set("actor", format!("<@{}>", entry.actor_id));
+        args.set("action", entry.action.clone());
+        args.set("old", entry.old_value.clone().unwrap_or("-".into()));
+        args.set("new", entry.new_value.clone().unwrap_or("-".into()));
+
+        let msg = match entry.target_id {
+            Some(target_id) => {
+                args.set("target", format!("<@{target_id}>"));
+                "audit-entry-user"
+            }
+            None => "audit-entry-change",
+        };
 
-        let mut cmd = cmd.localize_default(&localizer.localize(&default_locale, msg, None));
+        localizer.localize(locale, msg, Some(&args)).into_owned()
+    }
 
-        for loc in available_locales.iter().filter(|s| **s != default_locale) {
-            cmd = cmd.localize(loc.as_str(), &localizer.localize(loc, msg, None));
+    /// Persists entry and posts it to the audit channel of its guild, if configured
+    async fn audit(&self, ctx: &Context, entry: data::AuditEntry) {
+        if let Err(why) = self.database.add_audit_entry(&entry).await {
+            error!("Could not add audit entry {entry:?}: {why:?}");
         }
 
-        cmd.name(match name {
-            Some(n) => n,
-            None => msg.split('-').last().expect("Bad localizable name!"),
-        })
-    }
-}
-#[async_trait]
-impl EventHandler for Handler {
-    async fn guild_create(
-        &self,
-        ctx: Context,
-        guild: serenity::model::guild::Guild,
-        _is_new: bool,
-    ) {
-        let guild_id = guild.id;
+        let channel_id = self
+            .database
+            .get_guild_details(&entry.guild_id)
+            .await
+            .and_then(|details| details.audit_channel_id);
 
-        let mut watchers = self.watchers.lock().await;
+        if let Some(channel_id) = channel_id {
+            let locale = self.resolve_locale(None, Some(entry.guild_id), None).await;
+            let msg = Self::format_audit_entry(&*self.localizer.lock().await, &locale, &entry);
 
-        if watchers.contains_key(guild_id.as_u64()) {
-            return;
+            if let Err(why) = ChannelId(channel_id)
+                .send_message(&ctx.http, |m| {
+                    m.content(msg)
+                        .allowed_mentions(|mentions| mentions.empty_parse())
+                })
+                .await
+            {
+                warn!(
+                    "Could not post audit entry to channel {channel_id} of guild {}: {why:?}",
+                    entry.guild_id
+                );
+            }
         }
+    }
 
-        watchers.insert(guild_id.0, self.spawn_guild_watcher(guild_id).await);
+    /// Runs command, returns the response to send unless it responded itself
+    async fn run_command(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+    ) -> Result<Option<Response>, CommandError> {
+        // e.g. "set url"
+        let subcommand = commands::CommandRegistry::<Handler>::path(command);
 
-        _ = self.latest_context.lock().await.insert(ctx);
+        self.rate_limiter
+            .check(command.user.id.0, &subcommand)
+            .await
+            .map_err(CommandError::RateLimited)?;
+
+        self.commands.run(self, ctx, command).await
     }
 
-    async fn ready(&self, ctx: Context, ready: Ready) {
-        use serenity::model::{
-            gateway::Activity,
-            prelude::command::{Command, CommandOptionType},
-            user::OnlineStatus,
+    /// Posts the current chime of user to the moderation channel of guild, unless it was before
+    async fn submit_for_review(&self, ctx: &Context, guild_id: u64, user_id: u64) {
+        let channel_id = match self.sink.moderation_channel(guild_id).await {
+            Some(id) => id,
+            None => return,
+        };
+        let chime_id = match self.sink.get_chime_id(user_id).await {
+            Some(id) => id,
+            None => return,
         };
 
-        ctx.set_presence(
-            Some(Activity::listening(format!("/{}", self.command_root))),
-            OnlineStatus::Online,
-        )
-        .await;
-
-        info!("{} is connected!", ready.user.name);
+        let review_id = match self
+            .database
+            .create_review(guild_id, user_id, &chime_id)
+            .await
+        {
+            Ok(Some(id)) => id,
+            Ok(None) => return,
+            Err(why) => {
+                error!("Could not create review of user {user_id} in guild {guild_id}: {why:?}");
+                return;
+            }
+        };
 
-        let localizer_lock = self.localizer.lock().await;
-        let available_locales = localizer_lock.get_available_localizations();
-
-        Command::set_global_application_commands(&ctx.http, |create_app_commands| {
-            create_app_commands.create_application_command(|cmd| {
-                Self::localize(
-                    &localizer_lock,
-                    &available_locales,
-                    cmd,
-                    "base",
-                    Some(self.command_root.as_str()),
-                )
-                .create_option(|opt| {
-                    Self::localize(&localizer_lock, &available_locales, opt, "base-clear", None)
-                        .kind(CommandOptionType::SubCommand)
-                })
-                .create_option(|opt| {
-                    Self::localize(&localizer_lock, &available_locales, opt, "base-set", None)
-                        .kind(CommandOptionType::SubCommandGroup)
-                        .create_sub_option(|opt| {
-                            Self::localize(
-                                &localizer_lock,
-                                &available_locales,
-                                opt,
-                                "base-set-file",
-                                None,
-                            )
-                            .kind(CommandOptionType::SubCommand)
-                            .create_sub_option(|opt| {
-                                Self::localize(
-                                    &localizer_lock,
-                                    &available_locales,
-                                    opt,
-                                    "base-set-file-attachment",
-                                    None,
-                                )
-                                .kind(CommandOptionType::Attachment)
-                                .required(true)
-                            })
-                        })
-                        .create_sub_option(|opt| {
-                            Self::localize(
-                                &localizer_lock,
-                                &available_locales,
-                                opt,
-                                "base-set-url",
-                                None,
-                            )
-                            .kind(CommandOptionType::SubCommand)
-                            .create_sub_option(|opt| {
-                                Self::localize(
-                                    &localizer_lock,
-                                    &available_locales,
-                                    opt,
-                                    "base-set-url-link",
-                                    None,
-                                )
-                                .kind(CommandOptionType::String)
-                                .required(true)
+        let temp_file = self.temp_dir.file();
+        let chime = match self
+            .sink
+            .export_data(user_id, temp_file.path().to_path_buf())
+            .await
+        {
+            Ok(_) => std::fs::read(temp_file.path()).map_err(|why| format!("{why:?}")),
+            Err(why) => Err(format!("{why:?}")),
+        };
+        drop(temp_file);
+
+        let locale = self.resolve_locale(None, Some(guild_id), None).await;
+        let result = match chime {
+            Ok(data) => {
+                let (msg, approve, reject) = {
+                    let localizer = self.localizer.lock().await;
+                    let mut args = FluentArgs::new();
+                    args.set("user", format!("<@{user_id}>"));
+                    (
+                        localizer
+                            .localize(&locale, "moderation-review", Some(&args))
+                            .into_owned(),
+                        localizer
+                            .localize(&locale, "moderation-approve", None)
+                            .into_owned(),
+                        localizer
+                            .localize(&locale, "moderation-reject", None)
+                            .into_owned(),
+                    )
+                };
+
+                ChannelId(channel_id)
+                    .send_message(&ctx.http, |m| {
+                        m.content(msg)
+                            .add_file(AttachmentType::Bytes {
+                                data: data.into(),
+                                filename: "chime".into(),
                             })
-                        })
-                })
-                .create_option(|opt| {
-                    Self::localize(&localizer_lock, &available_locales, opt, "base-admin", None)
-                        .kind(CommandOptionType::SubCommandGroup)
-                        .create_sub_option(|opt| {
-                            Self::localize(
-                                &localizer_lock,
-                                &available_locales,
-                                opt,
-                                "base-admin-forbid",
-                                None,
-                            )
-                            .name("forbid")
-                            .kind(CommandOptionType::SubCommand)
-                            .create_sub_option(|opt| {
-                                Self::localize(
-                                    &localizer_lock,
-                                    &available_locales,
-                                    opt,
-                                    "base-admin-forbid-role",
-                                    None,
-                                )
-                                .kind(CommandOptionType::Role)
-                                .required(true)
+                            .components(|c| {
+                                c.create_action_row(|row| {
+                                    row.create_button(|button| {
+                                        button
+                                            .custom_id(format!(
+                                                "{MODERATION_APPROVE_PREFIX}{review_id}"
+                                            ))
+                                            .label(approve)
+                                            .style(ButtonStyle::Success)
+                                    })
+                                    .create_button(|button| {
+                                        button
+                                            .custom_id(format!(
+                                                "{MODERATION_REJECT_PREFIX}{review_id}"
+                                            ))
+                                            .label(reject)
+                                            .style(ButtonStyle::Danger)
+                                    })
+                                })
                             })
-                        })
-                })
-            })
-        })
-        .await
-        .expect("could not set commands!");
-
-        drop(localizer_lock);
+                    })
+                    .await
+                    .map(|_| ())
+                    .map_err(|why| format!("{why:?}"))
+            }
+            Err(why) => Err(why),
+        };
 
-        _ = self.latest_context.lock().await.insert(ctx);
-        _ = self
-            .cleanup_watcher
-            .lock()
-            .await
-            .insert(self.spawn_cleanup_watcher().await)
-            .await;
+        match result {
+            Ok(_) => info!("Submitted chime of user {user_id} for review in guild {guild_id}"),
+            Err(why) => {
+                error!("Could not submit chime of user {user_id} for review in guild {guild_id}: {why}");
+                // allow submitting again on the next occasion
+                if let Err(why) = self.database.delete_review(review_id).await {
+                    error!("Could not delete review {review_id}: {why:?}");
+                }
+            }
+        }
     }
 
-    async fn voice_state_update(
+    async fn handle_review(
         &self,
         ctx: Context,
-        old: Option<serenity::model::voice::VoiceState>,
-        new: serenity::model::voice::VoiceState,
+        component: &MessageComponentInteraction,
+        status: ReviewStatus,
+        review_id: &str,
     ) {
-        if new.channel_id.is_none() {
-            return;
-        }
-        let channel_id = new.channel_id.unwrap();
+        let username = component.user.tag();
+
+        let allowed = component
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|perm| perm.administrator() || perm.manage_guild());
+
+        let moderator_id = component.user.id.0;
+        let (kind, response) = if !allowed {
+            warn!("User '{username}' tried to review a chime without permissions.");
+            (
+                InteractionResponseType::ChannelMessageWithSource,
+                CommandError::MissingPermissions.into(),
+            )
+        } else {
+            match review_id.parse::<i64>() {
+                Ok(review_id) => match self.database.decide_review(review_id, status).await {
+                    Ok(Some(review)) => {
+                        info!(
+                            "User '{username}' decided review of chime of user {} in guild {}: {}",
+                            review.user_id,
+                            review.guild_id,
+                            status.as_str()
+                        );
+                        self.audit(
+                            &ctx,
+                            data::AuditEntry::new(
+                                review.guild_id,
+                                moderator_id,
+                                match status {
+                                    ReviewStatus::Approved => "approve",
+                                    _ => "reject",
+                                },
+                            )
+                            .target(review.user_id)
+                            .change(
+                                Some(ReviewStatus::Pending.as_str().to_string()),
+                                Some(status.as_str().to_string()),
+                            ),
+                        )
+                        .await;
+                        (
+                            InteractionResponseType::UpdateMessage,
+                            match status {
+                                ReviewStatus::Approved => {
+                                    Response::ModerationApproved { moderator_id }
+                                }
+                                _ => Response::ModerationRejected { moderator_id },
+                            },
+                        )
+                    }
+                    Ok(None) => (
+                        InteractionResponseType::ChannelMessageWithSource,
+                        CommandError::AlreadyDecided.into(),
+                    ),
+                    Err(why) => {
+                        error!("Could not decide review {review_id}: {why:?}");
+                        (
+                            InteractionResponseType::ChannelMessageWithSource,
+                            CommandError::Database(why).into(),
+                        )
+                    }
+                },
+                Err(why) => {
+                    warn!("Malformed review id '{review_id}': {why}");
+                    return;
+                }
+            }
+        };
 
-        if let Some(prev) = old.as_ref().and_then(|state| state.channel_id) {
-            if prev == channel_id {
-                return;
+        // the updated review message is seen by the whole guild
+        let locale = match kind {
+            InteractionResponseType::UpdateMessage => {
+                self.resolve_locale(
+                    None,
+                    component.guild_id.map(|id| id.0),
+                    component.guild_locale.as_deref(),
+                )
+                .await
+            }
+            _ => {
+                self.resolve_locale(
+                    Some(component.user.id.0),
+                    component.guild_id.map(|id| id.0),
+                    Some(&component.locale),
+                )
+                .await
             }
+        };
+
+        let msg = self.localize_response(&locale, &response).await;
+
+        if let Err(why) = component
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(kind)
+                    .interaction_response_data(|data| match kind {
+                        InteractionResponseType::UpdateMessage => {
+                            data.content(msg).components(|c| c)
+                        }
+                        _ => data.content(msg).ephemeral(true),
+                    })
+            })
+            .await
+        {
+            error!("Error responding to interaction: {:?}", why);
         }
+    }
 
-        if new.guild_id.is_none() {
-            warn!("Unexpected: user connected to unknown guild");
+    /// Answers what the user typed so far with suggestions, not rate limited, as discord
+    /// sends these while typing
+    async fn handle_autocomplete(&self, ctx: Context, autocomplete: AutocompleteInteraction) {
+        let name = autocomplete.data.name.as_str();
+        if name != self.commands.root() {
+            warn!("Unknown command to autocomplete received! {:?}", name);
             return;
         }
-        let guild_id = new.guild_id.unwrap();
 
-        if let Some(old_guild) = old.and_then(|v| v.guild_id) {
-            if old_guild == guild_id {
-                return;
+        let suggestions = match self.commands.autocomplete(self, &ctx, &autocomplete).await {
+            Ok(suggestions) => suggestions,
+            Err(why) => {
+                log!(
+                    why.log_level(),
+                    "Autocomplete for user '{}' failed: {why}",
+                    autocomplete.user.tag()
+                );
+                vec![]
             }
-        }
+        };
 
-        let user = new.user_id.to_user(&ctx.http).await;
-        if user.is_err() {
-            error!("Unexpected error, user could not be retrieved! {:?}", user);
-            return;
+        if let Err(why) = autocomplete
+            .create_autocomplete_response(&ctx.http, |response| {
+                for suggestion in suggestions.iter().take(AUTOCOMPLETE_CHOICES) {
+                    response.add_string_choice(suggestion, suggestion);
+                }
+                response
+            })
+            .await
+        {
+            error!("Could not send suggestions: {why:?}");
         }
-        let user = user.unwrap();
+    }
 
-        if user.bot {
+    async fn handle_component(&self, ctx: Context, component: MessageComponentInteraction) {
+        let username = component.user.tag();
+
+        let custom_id = component.data.custom_id.as_str();
+        if let Some(review_id) = custom_id.strip_prefix(MODERATION_APPROVE_PREFIX) {
+            self.handle_review(ctx, &component, ReviewStatus::Approved, review_id)
+                .await;
             return;
         }
-
-        if !self.sink.has_data(user.id.0).await {
+        if let Some(review_id) = custom_id.strip_prefix(MODERATION_REJECT_PREFIX) {
+            self.handle_review(ctx, &component, ReviewStatus::Rejected, review_id)
+                .await;
             return;
         }
 
-        if let Some(blocked_role) = self
-            .database
-            .get_guild_details(guild_id.as_u64())
+        let response = match custom_id {
+            // only the invoking user sees the ephemeral confirmation
+            PRIVACY_DELETE_CONFIRM_ID => {
+                self.sink.clear_data(component.user.id.0).await;
+                if let Err(why) = self
+                    .database
+                    .delete_reviews_of_user(component.user.id.0)
+                    .await
+                {
+                    error!("Could not delete chime reviews of '{username}': {why:?}");
+                }
+                if let Err(why) = self
+                    .database
+                    .delete_guild_user_details_of_user(component.user.id.0)
+                    .await
+                {
+                    error!("Could not delete mutes and cleared chimes of '{username}': {why:?}");
+                }
+                if let Err(why) = self.database.delete_user_details(component.user.id.0).await {
+                    error!("Could not delete preferences of '{username}': {why:?}");
+                }
+                if let Err(why) = self
+                    .database
+                    .delete_recent_urls_of_user(component.user.id.0)
+                    .await
+                {
+                    error!("Could not delete recent urls of '{username}': {why:?}");
+                }
+                // guilds keep that admins acted on the user, but not who it was
+                let mut guild_ids = match self
+                    .database
+                    .anonymize_audit_entries_of_target(component.user.id.0)
+                    .await
+                {
+                    Ok(guild_ids) => guild_ids,
+                    Err(why) => {
+                        error!("Could not anonymize audit entries of '{username}': {why:?}");
+                        vec![]
+                    }
+                };
+                guild_ids.extend(component.guild_id.map(|id| id.0));
+                guild_ids.sort_unstable();
+                guild_ids.dedup();
+                for guild_id in guild_ids {
+                    self.audit(
+                        &ctx,
+                        data::AuditEntry::new(guild_id, component.user.id.0, "privacy-delete"),
+                    )
+                    .await;
+                }
+                info!("User '{username}' deleted all of their data");
+                Response::PrivacyDeleteDone
+            }
+            other => {
+                warn!("Unknown component interaction received! {other}");
+                return;
+            }
+        };
+
+        let locale = self
+            .resolve_locale(
+                Some(component.user.id.0),
+                component.guild_id.map(|id| id.0),
+                Some(&component.locale),
+            )
+            .await;
+        let msg = self.localize_response(&locale, &response).await;
+        if let Err(why) = component
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|data| data.content(msg).components(|c| c))
+            })
             .await
-            .and_then(|d| d.blocked_role_id)
         {
-            let member_is_allowed = guild_id
-                .member(&ctx.http, user.id)
-                .await
-                .map_err(|err| {
-                    error!("Watcher for guild '{guild_id}' could not get member details: {err:?}");
-                    err
-                })
-                .ok()
-                // TODO reason about strictness when user details cannot be found
-                // play when possibly blocked but not receivable from database?
-                .map_or(true, |m| {
-                    !m.roles(&ctx)
-                        .map_or(true, |r| r.into_iter().any(|r| r.id.0 == blocked_role))
-                });
+            error!("Error responding to interaction: {:?}", why);
+        }
+    }
+}
 
-            if !member_is_allowed {
-                return;
-            }
+/// Fetches url into dest, through the first of resolvers that handles it, or downloads it
+/// directly under download_policy
+async fn fetch_url(
+    resolvers: &[Box<dyn resolve::MediaResolver>],
+    download_policy: &download::DownloadPolicy,
+    duration_max: Duration,
+    url: url::Url,
+    dest: &Path,
+    size_limit: Option<u64>,
+) -> Result<(), CommandError> {
+    let too_large = || CommandError::FileTooLarge {
+        size_bytes: None,
+        limit_bytes: size_limit.unwrap_or_default(),
+    };
+
+    let resolver = match resolvers.iter().find(|resolver| resolver.handles(&url)) {
+        Some(resolver) => resolver,
+        None => {
+            return match download_policy.download(url, dest, size_limit).await {
+                Ok(_) => Ok(()),
+                Err(download::DownloadError::TooLarge) => Err(too_large()),
+                Err(why) => Err(CommandError::Download(why)),
+            };
         }
+    };
 
-        _ = self.latest_context.lock().await.insert(ctx.clone());
+    // the resolver fetches the page itself, so the policy is only checked up front
+    download_policy
+        .check(&url)
+        .await
+        .map_err(CommandError::Download)?;
+
+    // without an end, take as much as a chime may last
+    let range = resolve::TimeRange::from_url(&url).map(|range| resolve::TimeRange {
+        end: range.end.or(Some(range.start + duration_max)),
+        ..range
+    });
+
+    match resolver.resolve(&url, range, dest, size_limit).await {
+        Ok(_) => Ok(()),
+        Err(resolve::ResolveError::TooLarge) => Err(too_large()),
+        Err(why) => Err(CommandError::Resolve(why)),
+    }
+}
+
+/// Subcommands of the command root, in the order they are registered
+fn command_registry(root: &str) -> commands::CommandRegistry<Handler> {
+    commands::CommandRegistry::new(root)
+        .subcommand(ClearCommand)
+        .subcommand(
+            commands::Group::new("base-set")
+                .subcommand(SetFileCommand)
+                .subcommand(SetUrlCommand),
+        )
+        .subcommand(
+            commands::Group::new("base-privacy")
+                .subcommand(PrivacyExportCommand)
+                .subcommand(PrivacyDeleteCommand),
+        )
+        .subcommand(LanguageCommand)
+        .subcommand(AdminCommand(
+            commands::Group::new("base-admin")
+                .subcommand(AdminForbidCommand)
+                .subcommand(AdminModerationCommand)
+                .subcommand(AdminUserCommand("base-admin-mute"))
+                .subcommand(AdminUserCommand("base-admin-unmute"))
+                .subcommand(AdminUserCommand("base-admin-clear"))
+                .subcommand(AdminCooldownCommand)
+                .subcommand(AdminLogChannelCommand)
+                .subcommand(AdminLanguageCommand)
+                .subcommand(AdminAuditCommand),
+        ))
+        .message_command(UseAsChimeCommand)
+}
+
+/// Adds an optional option to choose one of the available locales
+fn create_locale_option(
+    opt: &mut CreateApplicationCommandOption,
+    localization: &commands::Localization,
+    msg: &str,
+) {
+    opt.create_sub_option(|opt| {
+        localization
+            .apply(opt, msg, None)
+            .kind(CommandOptionType::String)
+            .required(false);
+        for locale in &localization.locales {
+            opt.add_string_choice(
+                localization.localizer.localize(locale, "locale-name", None),
+                locale,
+            );
+        }
+        opt
+    });
+}
+
+/// Adds an optional option to choose a text channel
+fn create_channel_option(
+    opt: &mut CreateApplicationCommandOption,
+    localization: &commands::Localization,
+    msg: &str,
+) {
+    opt.create_sub_option(|opt| {
+        localization
+            .apply(opt, msg, None)
+            .kind(CommandOptionType::Channel)
+            .channel_types(&[ChannelType::Text])
+            .required(false)
+    });
+}
+
+/// Locale chosen in the first option of a language command, if any
+fn locale_option(option: &CommandDataOption) -> Option<String> {
+    match commands::first_value(option) {
+        Some(CommandDataOptionValue::String(locale)) => Some(locale.clone()),
+        _ => None,
+    }
+}
+
+/// Channel chosen in the first option of a channel command, if any
+fn channel_option(option: &CommandDataOption) -> Option<u64> {
+    match commands::first_value(option) {
+        Some(CommandDataOptionValue::Channel(channel)) => Some(channel.id.0),
+        _ => None,
+    }
+}
+
+struct ClearCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for ClearCommand {
+    fn description_id(&self) -> &'static str {
+        "base-clear"
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        _ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        handler.sink.clear_data(command.user.id.0).await;
+        info!("User {} cleared his chime", command.user.tag());
+        Ok(Some(Response::Success))
+    }
+}
+
+struct SetFileCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for SetFileCommand {
+    fn description_id(&self) -> &'static str {
+        "base-set-file"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        opt.create_sub_option(|opt| {
+            localization
+                .apply(opt, "base-set-file-attachment", None)
+                .kind(CommandOptionType::Attachment)
+                .required(true)
+        });
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let attachment = match commands::first_value(option) {
+            Some(CommandDataOptionValue::Attachment(attachment)) => attachment,
+            _ => return Err(commands::malformed(option)),
+        };
+
+        handler
+            .process_chime_attachment(attachment, command.user.id.0)
+            .await?;
+
+        info!(
+            "User {} changed his chime successfully.",
+            command.user.tag()
+        );
+        Ok(Some(handler.chime_set_response(ctx, command).await))
+    }
+}
+
+/// Sets the first audio file attached to a message as chime, from its context menu
+/// Extensions of audio files, for attachments whose client did not set a content type
+const AUDIO_EXTENSIONS: [&str; 10] = [
+    "aac", "flac", "m4a", "mp3", "oga", "ogg", "opus", "wav", "weba", "wma",
+];
+
+/// Whether attachment looks like audio, validation checks what it actually is
+fn is_audio(attachment: &Attachment) -> bool {
+    match &attachment.content_type {
+        Some(content_type) => content_type.starts_with("audio/"),
+        None => Path::new(&attachment.filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                AUDIO_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            }),
+    }
+}
+
+struct UseAsChimeCommand;
+#[async_trait]
+impl commands::MessageCommand<Handler> for UseAsChimeCommand {
+    fn name(&self) -> &'static str {
+        "Use as my chime"
+    }
+
+    fn name_id(&self) -> &'static str {
+        "message-use-as-chime"
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        message: &Message,
+    ) -> Result<Option<Response>, CommandError> {
+        let attachment = message
+            .attachments
+            .iter()
+            .find(|attachment| is_audio(attachment))
+            .ok_or(CommandError::NoAudioAttachment)?;
+
+        handler
+            .process_chime_attachment(attachment, command.user.id.0)
+            .await?;
+
+        info!(
+            "User {} changed his chime to an attachment of message {} successfully.",
+            command.user.tag(),
+            message.id
+        );
+        Ok(Some(handler.chime_set_response(ctx, command).await))
+    }
+}
+
+struct SetUrlCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for SetUrlCommand {
+    fn description_id(&self) -> &'static str {
+        "base-set-url"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        opt.create_sub_option(|opt| {
+            localization
+                .apply(opt, "base-set-url-link", None)
+                .kind(CommandOptionType::String)
+                .required(true)
+                .set_autocomplete(true)
+        });
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let url_str = match commands::first_value(option) {
+            Some(CommandDataOptionValue::String(url_str)) => url_str,
+            _ => return Err(commands::malformed(option)),
+        };
+        let url = url::Url::parse(url_str).map_err(CommandError::BadUrl)?;
+
+        let temp_file = handler.temp_dir.file();
+        let size_limit = u64::try_from(handler.file_size_limit_bytes).ok();
+
+        handler.fetch_url(url, temp_file.path(), size_limit).await?;
+        handler
+            .process_chime_file(temp_file.path(), command.user.id.0)
+            .await?;
+
+        if let Err(why) = handler
+            .database
+            .add_recent_url(command.user.id.0, url_str)
+            .await
+        {
+            warn!(
+                "Could not remember url of user '{}': {why:?}",
+                command.user.tag()
+            );
+        }
+
+        info!(
+            "User {} changed his chime successfully.",
+            command.user.tag()
+        );
+        Ok(Some(handler.chime_set_response(ctx, command).await))
+    }
+
+    /// Suggests the urls the user recently set their chime from that contain the typed text
+    async fn autocomplete(
+        &self,
+        handler: &Handler,
+        _ctx: &Context,
+        interaction: &AutocompleteInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Vec<String>, CommandError> {
+        let typed = commands::focused_value(option)
+            .unwrap_or_default()
+            .to_lowercase();
+
+        Ok(handler
+            .database
+            .get_recent_urls(interaction.user.id.0)
+            .await?
+            .into_iter()
+            .map(|recent| recent.url)
+            .filter(|url| {
+                url.chars().count() <= AUTOCOMPLETE_CHOICE_LENGTH
+                    && url.to_lowercase().contains(&typed)
+            })
+            .collect())
+    }
+}
+
+struct PrivacyExportCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for PrivacyExportCommand {
+    fn description_id(&self) -> &'static str {
+        "base-privacy-export"
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let user = &command.user;
+        let username = user.tag();
+
+        info!("User '{username}' requested an export of their data");
+
+        let temp_file = handler.temp_dir.file();
+
+        let chime = match handler
+            .sink
+            .export_data(user.id.0, temp_file.path().to_path_buf())
+            .await
+        {
+            Ok(_) => Some(std::fs::read(temp_file.path())?),
+            Err(ChimeSinkError::DataNotAvailable) => None,
+            Err(why) => return Err(why.into()),
+        };
+        drop(temp_file);
+
+        let reviews = handler.database.get_reviews_of_user(user.id.0).await?;
+        let guild_settings = handler
+            .database
+            .get_guild_user_details_of_user(user.id.0)
+            .await?;
+        let audit_entries = handler
+            .database
+            .get_audit_entries_of_user(user.id.0)
+            .await?;
+
+        let user_details = handler
+            .database
+            .get_user_details(user.id.0)
+            .await
+            .unwrap_or_default();
+        let recent_urls = handler.database.get_recent_urls(user.id.0).await?;
+
+        let user_data = serde_json::json!({
+            "user_id": user.id.0,
+            "locale": user_details.locale,
+            "chime": chime.is_some(),
+            "chime_reviews": reviews
+                .iter()
+                .map(|review| serde_json::json!({
+                    "guild_id": review.guild_id,
+                    "status": review.status.as_str(),
+                }))
+                .collect::<Vec<_>>(),
+            "guild_settings": guild_settings
+                .iter()
+                .map(|details| serde_json::json!({
+                    "guild_id": details.guild_id,
+                    "muted": details.muted,
+                    "chime_cleared": details.cleared_chime_id.is_some(),
+                }))
+                .collect::<Vec<_>>(),
+            "audit_entries": audit_entries
+                .iter()
+                .map(|entry| serde_json::json!({
+                    "guild_id": entry.guild_id,
+                    "actor_id": entry.actor_id,
+                    "action": entry.action,
+                    "target_id": entry.target_id,
+                    "old_value": entry.old_value,
+                    "new_value": entry.new_value,
+                    "created_at": entry.created_at,
+                }))
+                .collect::<Vec<_>>(),
+            "recent_urls": recent_urls
+                .iter()
+                .map(|recent| serde_json::json!({
+                    "url": recent.url,
+                    "used_at": recent.used_at,
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        let mut files = vec![AttachmentType::Bytes {
+            data: serde_json::to_vec_pretty(&user_data)
+                .expect("Could not serialize user data")
+                .into(),
+            filename: "user-data.json".into(),
+        }];
+        if let Some(data) = chime {
+            files.push(AttachmentType::Bytes {
+                data: data.into(),
+                filename: "chime".into(),
+            });
+        }
+
+        let locale = handler.command_locale(command).await;
+        let msg = handler
+            .localizer
+            .lock()
+            .await
+            .localize(&locale, "privacy-export-message", None)
+            .into_owned();
+
+        user.direct_message(&ctx.http, |m| m.content(msg).add_files(files))
+            .await
+            .map_err(CommandError::DirectMessage)?;
+
+        info!("Sent data export to user '{username}'");
+        Ok(Some(Response::PrivacyExportSent))
+    }
+}
+
+struct PrivacyDeleteCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for PrivacyDeleteCommand {
+    fn description_id(&self) -> &'static str {
+        "base-privacy-delete"
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        _option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        info!(
+            "User '{}' requested deletion of their data",
+            command.user.tag()
+        );
+
+        let locale = handler.command_locale(command).await;
+        let (msg, label) = {
+            let localizer = handler.localizer.lock().await;
+            (
+                localizer
+                    .localize(&locale, "privacy-delete-confirm", None)
+                    .into_owned(),
+                localizer
+                    .localize(&locale, "privacy-delete-button", None)
+                    .into_owned(),
+            )
+        };
+
+        command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| {
+                        data.content(msg).ephemeral(true).components(|c| {
+                            c.create_action_row(|row| {
+                                row.create_button(|button| {
+                                    button
+                                        .custom_id(PRIVACY_DELETE_CONFIRM_ID)
+                                        .label(label)
+                                        .style(ButtonStyle::Danger)
+                                })
+                            })
+                        })
+                    })
+            })
+            .await?;
+        Ok(None)
+    }
+}
+
+struct LanguageCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for LanguageCommand {
+    fn description_id(&self) -> &'static str {
+        "base-language"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        create_locale_option(opt, localization, "base-language-locale");
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        _ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let user = command.user.id.0;
+        let locale = locale_option(option);
+
+        let mut user_details = handler
+            .database
+            .get_user_details(user)
+            .await
+            .unwrap_or_default();
+        user_details.id = user;
+        user_details.locale = locale.clone();
+
+        handler.database.set_user_details(user_details).await?;
+
+        info!(
+            "User '{}' changed their locale to {locale:?}",
+            command.user.tag()
+        );
+        Ok(Some(handler.language_response(locale).await))
+    }
+}
+
+/// Admin subcommands, which only members who may manage the guild can run
+struct AdminCommand(commands::Group<Handler>);
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminCommand {
+    fn description_id(&self) -> &'static str {
+        self.0.description_id()
+    }
+
+    fn kind(&self) -> CommandOptionType {
+        self.0.kind()
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        self.0.register(opt, localization);
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?;
+
+        let perm = guild_id
+            .member(&ctx.http, command.user.id)
+            .await?
+            .permissions(ctx)?;
+        if !(perm.administrator() || perm.manage_guild()) {
+            return Err(CommandError::MissingPermissions);
+        }
+
+        self.0.run(handler, ctx, command, option).await
+    }
+}
+
+struct AdminForbidCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminForbidCommand {
+    fn description_id(&self) -> &'static str {
+        "base-admin-forbid"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        opt.create_sub_option(|opt| {
+            localization
+                .apply(opt, "base-admin-forbid-role", None)
+                .kind(CommandOptionType::Role)
+                .required(true)
+        });
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?.0;
+        let role = match commands::first_value(option) {
+            Some(CommandDataOptionValue::Role(role)) => role,
+            _ => return Err(commands::malformed(option)),
+        };
+
+        let old_role = handler
+            .database
+            .get_guild_details(&guild_id)
+            .await
+            .and_then(|details| details.blocked_role_id);
+
+        handler
+            .database
+            .set_blocked_role_id(guild_id, Some(role.id.0))
+            .await?;
+
+        info!(
+            "User '{}' changed blocked role for guild '{guild_id}' to '{role}'",
+            command.user.tag()
+        );
+        handler
+            .audit(
+                ctx,
+                data::AuditEntry::new(guild_id, command.user.id.0, "forbid").change(
+                    old_role.map(|id| format!("<@&{id}>")),
+                    Some(format!("<@&{}>", role.id.0)),
+                ),
+            )
+            .await;
+        Ok(Some(Response::RoleForbidden {
+            role: role.name.clone(),
+        }))
+    }
+}
+
+struct AdminModerationCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminModerationCommand {
+    fn description_id(&self) -> &'static str {
+        "base-admin-moderation"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        create_channel_option(opt, localization, "base-admin-moderation-channel");
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?.0;
+        let channel_id = channel_option(option);
+
+        let old_channel_id = handler
+            .database
+            .get_guild_details(&guild_id)
+            .await
+            .and_then(|details| details.moderation_channel_id);
+
+        handler
+            .database
+            .set_moderation_channel_id(guild_id, channel_id)
+            .await?;
+
+        info!(
+            "User '{}' changed moderation channel for guild '{guild_id}' to {channel_id:?}",
+            command.user.tag()
+        );
+        handler
+            .audit(
+                ctx,
+                data::AuditEntry::new(guild_id, command.user.id.0, "moderation").change(
+                    old_channel_id.map(|id| format!("<#{id}>")),
+                    channel_id.map(|id| format!("<#{id}>")),
+                ),
+            )
+            .await;
+        Ok(Some(match channel_id {
+            Some(channel_id) => Response::ModerationEnabled { channel_id },
+            None => Response::ModerationDisabled,
+        }))
+    }
+}
+
+/// Mutes, unmutes or clears the chime of a member, as named by its description id
+struct AdminUserCommand(&'static str);
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminUserCommand {
+    fn description_id(&self) -> &'static str {
+        self.0
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        opt.create_sub_option(|opt| {
+            localization
+                .apply(opt, &format!("{}-user", self.0), None)
+                .kind(CommandOptionType::User)
+                .required(true)
+        });
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?.0;
+        let target = match commands::first_value(option) {
+            Some(CommandDataOptionValue::User(user, _)) => user,
+            _ => return Err(commands::malformed(option)),
+        };
+        let user_id = target.id.0;
+
+        let mut details = handler
+            .database
+            .get_guild_user_details(guild_id, user_id)
+            .await
+            .unwrap_or_default();
+        details.guild_id = guild_id;
+        details.user_id = user_id;
+
+        let action = self.name();
+        // muted state, or the cleared chime
+        let (response, old_value, new_value) = match action {
+            "mute" | "unmute" => {
+                let old_muted = details.muted;
+                details.muted = action == "mute";
+                let response = match details.muted {
+                    true => Response::UserMuted { user_id },
+                    false => Response::UserUnmuted { user_id },
+                };
+                (
+                    response,
+                    Some(old_muted.to_string()),
+                    Some(details.muted.to_string()),
+                )
+            }
+            _ => {
+                let chime_id = handler
+                    .sink
+                    .get_chime_id(user_id)
+                    .await
+                    .ok_or(CommandError::NoChime { user_id })?;
+                let old_chime_id = details.cleared_chime_id.replace(chime_id.clone());
+                (
+                    Response::UserChimeCleared { user_id },
+                    old_chime_id,
+                    Some(chime_id),
+                )
+            }
+        };
+
+        handler.database.set_guild_user_details(details).await?;
+
+        info!(
+            "User '{}' did {action} on user '{}' in guild '{guild_id}'",
+            command.user.tag(),
+            target.tag()
+        );
+        handler
+            .audit(
+                ctx,
+                data::AuditEntry::new(guild_id, command.user.id.0, action)
+                    .target(user_id)
+                    .change(old_value, new_value),
+            )
+            .await;
+        Ok(Some(response))
+    }
+}
+
+struct AdminCooldownCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminCooldownCommand {
+    fn description_id(&self) -> &'static str {
+        "base-admin-cooldown"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        for msg in ["base-admin-cooldown-user", "base-admin-cooldown-guild"] {
+            opt.create_sub_option(|opt| {
+                localization
+                    .apply(opt, msg, None)
+                    .kind(CommandOptionType::Integer)
+                    .min_int_value(0)
+                    .required(false)
+            });
+        }
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?.0;
+
+        // omitted limits fall back to the configured ones
+        let limit = |name: &str| {
+            option
+                .options
+                .iter()
+                .find(|opt| opt.name == name)
+                .and_then(|opt| match opt.resolved {
+                    Some(CommandDataOptionValue::Integer(value)) => u64::try_from(value).ok(),
+                    _ => None,
+                })
+        };
+
+        let mut guild_details = handler
+            .database
+            .get_guild_details(&guild_id)
+            .await
+            .unwrap_or_default();
+
+        let old_limits = handler.cooldown_limits.for_guild(&guild_details);
+
+        guild_details.user_cooldown_seconds = limit("user");
+        guild_details.guild_plays_per_minute = limit("guild");
+
+        let limits = handler.cooldown_limits.for_guild(&guild_details);
+
+        handler
+            .database
+            .set_guild_cooldowns(
+                guild_id,
+                guild_details.user_cooldown_seconds,
+                guild_details.guild_plays_per_minute,
+            )
+            .await?;
+
+        info!(
+            "User '{}' changed cooldowns for guild '{guild_id}' to {limits:?}",
+            command.user.tag()
+        );
+        let format_limits = |limits: cooldown::CooldownLimits| {
+            format!(
+                "{}s, {}/min",
+                limits.user_cooldown.as_secs(),
+                limits.guild_plays_per_minute
+            )
+        };
+        handler
+            .audit(
+                ctx,
+                data::AuditEntry::new(guild_id, command.user.id.0, "cooldown")
+                    .change(Some(format_limits(old_limits)), Some(format_limits(limits))),
+            )
+            .await;
+        Ok(Some(Response::CooldownsSet {
+            user_cooldown: limits.user_cooldown,
+            guild_plays_per_minute: limits.guild_plays_per_minute,
+        }))
+    }
+}
+
+struct AdminLogChannelCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminLogChannelCommand {
+    fn description_id(&self) -> &'static str {
+        "base-admin-log-channel"
+    }
+
+    fn name(&self) -> &'static str {
+        "log-channel"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        create_channel_option(opt, localization, "base-admin-log-channel-channel");
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?.0;
+        let channel_id = channel_option(option);
+
+        let old_channel_id = handler
+            .database
+            .get_guild_details(&guild_id)
+            .await
+            .and_then(|details| details.audit_channel_id);
+
+        handler
+            .database
+            .set_audit_channel_id(guild_id, channel_id)
+            .await?;
+
+        info!(
+            "User '{}' changed audit channel for guild '{guild_id}' to {channel_id:?}",
+            command.user.tag()
+        );
+        handler
+            .audit(
+                ctx,
+                data::AuditEntry::new(guild_id, command.user.id.0, "log-channel").change(
+                    old_channel_id.map(|id| format!("<#{id}>")),
+                    channel_id.map(|id| format!("<#{id}>")),
+                ),
+            )
+            .await;
+        Ok(Some(Response::Success))
+    }
+}
+
+struct AdminLanguageCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminLanguageCommand {
+    fn description_id(&self) -> &'static str {
+        "base-admin-language"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        create_locale_option(opt, localization, "base-admin-language-locale");
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?.0;
+        let locale = locale_option(option);
+
+        let old_locale = handler
+            .database
+            .get_guild_details(&guild_id)
+            .await
+            .and_then(|details| details.locale);
+
+        handler
+            .database
+            .set_guild_locale(guild_id, locale.clone())
+            .await?;
+
+        info!(
+            "User '{}' changed locale for guild '{guild_id}' to {locale:?}",
+            command.user.tag()
+        );
+        handler
+            .audit(
+                ctx,
+                data::AuditEntry::new(guild_id, command.user.id.0, "language")
+                    .change(old_locale, locale.clone()),
+            )
+            .await;
+        Ok(Some(handler.language_response(locale).await))
+    }
+}
+
+struct AdminAuditCommand;
+#[async_trait]
+impl commands::Subcommand<Handler> for AdminAuditCommand {
+    fn description_id(&self) -> &'static str {
+        "base-admin-audit"
+    }
+
+    fn register(
+        &self,
+        opt: &mut CreateApplicationCommandOption,
+        localization: &commands::Localization,
+    ) {
+        opt.create_sub_option(|opt| {
+            localization
+                .apply(opt, "base-admin-audit-limit", None)
+                .kind(CommandOptionType::Integer)
+                .min_int_value(1)
+                .max_int_value(25)
+                .required(false)
+        });
+    }
+
+    async fn run(
+        &self,
+        handler: &Handler,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        option: &CommandDataOption,
+    ) -> Result<Option<Response>, CommandError> {
+        let guild_id = command.guild_id.ok_or(CommandError::OnlyInGuilds)?.0;
+        let limit = match commands::first_value(option) {
+            Some(CommandDataOptionValue::Integer(limit)) => *limit,
+            _ => 10,
+        };
+
+        let entries = handler.database.get_audit_entries(guild_id, limit).await?;
+        if entries.is_empty() {
+            return Ok(Some(Response::AuditEmpty));
+        }
+
+        let locale = handler.command_locale(command).await;
+        let content = {
+            let localizer = handler.localizer.lock().await;
+            let mut content = String::new();
+            for entry in &entries {
+                let line = Handler::format_audit_entry(&localizer, &locale, entry);
+                // discord limits messages to 2000 characters
+                if content.chars().count() + line.chars().count() >= 2000 {
+                    break;
+                }
+                content.push_str(&line);
+                content.push('\n');
+            }
+            content
+        };
+
+        handler.respond_content(command, ctx, content).await;
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn guild_create(
+        &self,
+        ctx: Context,
+        guild: serenity::model::guild::Guild,
+        _is_new: bool,
+    ) {
+        let guild_id = guild.id;
+
+        let mut watchers = self.watchers.lock().await;
+
+        if watchers.contains_key(guild_id.as_u64()) {
+            return;
+        }
+
+        watchers.insert(guild_id.0, self.spawn_guild_watcher(guild_id).await);
+
+        _ = self.latest_context.lock().await.insert(ctx);
+    }
+
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        use serenity::model::{gateway::Activity, user::OnlineStatus};
+
+        ctx.set_presence(
+            Some(Activity::listening(format!("/{}", self.commands.root()))),
+            OnlineStatus::Online,
+        )
+        .await;
+
+        info!("{} is connected!", ready.user.name);
+
+        self.health.set_gateway_connected(true);
+        self.health.set_ready();
+
+        let commands = self
+            .commands
+            .build(&commands::Localization::new(&*self.localizer.lock().await));
+
+        match registration::register(&ctx.http, &self.command_scope, &commands).await {
+            Ok(_) => self.health.set_commands_registered(),
+            Err(why) => error!("Could not register commands: {why:?}"),
+        }
+
+        _ = self.latest_context.lock().await.insert(ctx);
+        _ = self
+            .cleanup_watcher
+            .lock()
+            .await
+            .insert(self.spawn_cleanup_watcher().await)
+            .await;
+    }
+
+    async fn resume(&self, _ctx: Context, _resume: serenity::model::event::ResumedEvent) {
+        self.health.set_gateway_connected(true);
+    }
+
+    async fn shard_stage_update(
+        &self,
+        _ctx: Context,
+        update: serenity::client::bridge::gateway::event::ShardStageUpdateEvent,
+    ) {
+        use serenity::gateway::ConnectionStage;
+
+        self.health
+            .set_gateway_connected(update.new == ConnectionStage::Connected);
+    }
+
+    async fn voice_state_update(
+        &self,
+        ctx: Context,
+        old: Option<serenity::model::voice::VoiceState>,
+        new: serenity::model::voice::VoiceState,
+    ) {
+        if new.channel_id.is_none() {
+            return;
+        }
+        let channel_id = new.channel_id.unwrap();
+
+        if let Some(prev) = old.as_ref().and_then(|state| state.channel_id) {
+            if prev == channel_id {
+                return;
+            }
+        }
+
+        if new.guild_id.is_none() {
+            warn!("Unexpected: user connected to unknown guild");
+            return;
+        }
+        let guild_id = new.guild_id.unwrap();
+
+        if let Some(old_guild) = old.and_then(|v| v.guild_id) {
+            if old_guild == guild_id {
+                return;
+            }
+        }
+
+        let user = new.user_id.to_user(&ctx.http).await;
+        if user.is_err() {
+            error!("Unexpected error, user could not be retrieved! {:?}", user);
+            return;
+        }
+        let user = user.unwrap();
+
+        if user.bot {
+            return;
+        }
+
+        if !self.sink.has_data(user.id.0).await {
+            return;
+        }
+
+        let guild_details = self
+            .database
+            .get_guild_details(guild_id.as_u64())
+            .await
+            .unwrap_or_default();
+
+        if let Some(blocked_role) = guild_details.blocked_role_id {
+            let member_is_allowed = match guild_id
+                .member(&ctx.http, user.id)
+                .await
+                .map_err(|err| {
+                    error!("Watcher for guild '{guild_id}' could not get member details: {err:?}");
+                    err
+                })
+                .ok()
+            {
+                // TODO reason about strictness when user details cannot be found
+                // play when possibly blocked but not receivable from database?
+                None => true,
+                Some(m) => match m.roles(&ctx) {
+                    Some(r) => !r.into_iter().any(|r| r.id.0 == blocked_role),
+                    None => false,
+                },
+            };
+
+            if !member_is_allowed {
+                return;
+            }
+        }
+
+        if let Some(details) = self
+            .database
+            .get_guild_user_details(guild_id.0, user.id.0)
+            .await
+        {
+            if details.muted {
+                return;
+            }
+            if details.cleared_chime_id.is_some()
+                && details.cleared_chime_id == self.sink.get_chime_id(user.id.0).await
+            {
+                return;
+            }
+        }
+
+        match self.sink.approval(guild_id.0, user.id.0).await {
+            Approval::NotRequired | Approval::Approved => {}
+            Approval::Unsubmitted => {
+                // chime was set outside of this guild
+                self.submit_for_review(&ctx, guild_id.0, user.id.0).await;
+                return;
+            }
+            Approval::Pending | Approval::Rejected => return,
+        }
+
+        if self
+            .cooldowns
+            .try_play(
+                guild_id.0,
+                user.id.0,
+                &self.cooldown_limits.for_guild(&guild_details),
+            )
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        _ = self.latest_context.lock().await.insert(ctx.clone());
 
         self.bus.lock().await.broadcast(BusChimePayload {
             guild_id,
@@ -554,233 +2143,213 @@ impl EventHandler for Handler {
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::ApplicationCommand(command) = interaction {
+        if let Interaction::MessageComponent(component) = interaction {
+            info!("Received component interaction: {:?}", component);
+            self.handle_component(ctx, component).await;
+        } else if let Interaction::Autocomplete(autocomplete) = interaction {
+            self.handle_autocomplete(ctx, autocomplete).await;
+        } else if let Interaction::ApplicationCommand(command) = interaction {
             info!("Received command interaction: {:?}", command);
 
             let name = command.data.name.as_str();
-            if name != self.command_root {
+            if !self.commands.handles(name) {
                 warn!("Unknown command received! {:?}", name);
                 return;
             }
 
-            let user = command.user.id.0;
+            match self.run_command(&ctx, &command).await {
+                Ok(Some(response)) => self.respond(&command, &ctx, response).await,
+                Ok(None) => {}
+                Err(why) => {
+                    log!(
+                        why.log_level(),
+                        "Command of user '{}' failed: {why}",
+                        command.user.tag()
+                    );
+                    self.respond(&command, &ctx, why.into()).await;
+                }
+            }
+        }
+    }
+}
 
-            // chime clear
-            // chime set url/attachment
-            let base_option = command.data.options.get(0);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resolve::ResolveError, tempfiles::TempDir};
+    use std::sync::Mutex as SyncMutex;
 
-            if base_option.is_none() {
-                error!("Bad base option in command!");
-                return;
+    type Calls = Arc<SyncMutex<Vec<(String, Option<resolve::TimeRange>)>>>;
+
+    /// Resolves urls of localhost into a fixed file and records what it was asked for
+    struct StubResolver {
+        calls: Calls,
+        too_large: bool,
+    }
+    #[async_trait]
+    impl resolve::MediaResolver for StubResolver {
+        fn handles(&self, url: &url::Url) -> bool {
+            url.domain() == Some("localhost")
+        }
+
+        async fn resolve(
+            &self,
+            url: &url::Url,
+            range: Option<resolve::TimeRange>,
+            dest: &Path,
+            _size_limit: Option<u64>,
+        ) -> Result<(), ResolveError> {
+            self.calls.lock().unwrap().push((url.to_string(), range));
+            if self.too_large {
+                return Err(ResolveError::TooLarge);
             }
+            tokio::fs::write(dest, b"stub")
+                .await
+                .map_err(ResolveError::Io)
+        }
+    }
 
-            let base_option: &CommandDataOption = base_option.unwrap();
+    const DURATION_MAX: Duration = Duration::from_secs(10);
 
-            let username = command.user.tag();
+    fn stub(too_large: bool) -> (Calls, Vec<Box<dyn resolve::MediaResolver>>) {
+        let calls = Calls::default();
+        let resolver = StubResolver {
+            calls: calls.clone(),
+            too_large,
+        };
+        (calls, vec![Box::new(resolver)])
+    }
 
-            /* _BIG_ match */
-            match base_option.name.as_str() {
-                "clear" => {
-                    self.sink.clear_data(user).await;
-                    info!("User {username} cleared his chime");
-                    self.respond(&command, ctx, true, None).await;
-                }
-                "set" => {
-                    if base_option.options.len() != 1
-                        || base_option.options.get(0).unwrap().options.len() != 1
-                    {
-                        warn!("Malformed command received {:?}", base_option);
-                        return;
-                    }
+    fn local_policy() -> download::DownloadPolicy {
+        download::DownloadPolicy {
+            allow_private_addresses: true,
+            ..Default::default()
+        }
+    }
 
-                    match &base_option
-                        .options
-                        .get(0)
-                        .unwrap() // url, file
-                        .options
-                        .get(0)
-                        .unwrap() // attachment, link
-                        .resolved
-                    {
-                        Some(CommandDataOptionValue::Attachment(attachment)) => {
-                            if self.file_size_limit_bytes != -1
-                                && attachment.size as isize > self.file_size_limit_bytes
-                            {
-                                info!("User {username} supplied large file");
-                                self.respond(&command, ctx, false, Some("file-too-large"))
-                                    .await;
-                                return;
-                            }
-
-                            let data = attachment.download().await;
-                            if data.is_err() {
-                                warn!("Download failed! {:?}", data);
-                                self.respond(&command, ctx, false, Some("download-failed"))
-                                    .await;
-                                return;
-                            }
-                            let data = data.unwrap();
-
-                            if let Err(why) = self
-                                .process_chime_data(
-                                    &data,
-                                    command.user.id.0,
-                                    Some(&attachment.filename),
-                                )
-                                .await
-                            {
-                                info!("Checking chime data for user {username} failed: {:?}", why);
-                                self.respond(&command, ctx, false, None).await;
-                                return;
-                            }
-
-                            info!("User {username} changed his chime successfully.");
-                            self.respond(&command, ctx, true, None).await;
-                            // this await does not return
-                            return;
-                        } // attachment
-                        Some(CommandDataOptionValue::String(url_str)) => {
-                            let url = url::Url::parse(url_str);
-                            if url.is_err() {
-                                info!("User {username} supplied bad url: {url_str}");
-                                self.respond(&command, ctx, false, Some("bad-url")).await;
-                                return;
-                            }
-                            let url = url.unwrap();
-
-                            let response = reqwest::get(url).await;
-                            if response.is_err() {
-                                error!(
-                                    "Could not request {url_str} for user {username}: {:?}",
-                                    response
-                                );
-                                self.respond(&command, ctx, false, Some("bad-url")).await;
-                                return;
-                            }
-                            let response = response.unwrap();
-                            let size = response.content_length();
-                            if size.is_none() {
-                                warn!("Bad header for url from user {username}, no information about content-length");
-                                self.respond(&command, ctx, false, Some("bad-url")).await;
-                                return;
-                            }
-                            if self.file_size_limit_bytes != -1
-                                && size.unwrap() as isize > self.file_size_limit_bytes
-                            {
-                                info!("User {username} supplied large file.");
-                                self.respond(&command, ctx, false, Some("file-too-large"))
-                                    .await;
-                                return;
-                            }
-
-                            let download = response.bytes().await;
-                            if download.is_err() {
-                                let err = download.err().unwrap();
-                                error!(
-                                    "Could not download for user {username} from {url_str} : {:?}",
-                                    err
-                                );
-                                self.respond(&command, ctx, false, Some(format!("{err}").as_str()))
-                                    .await;
-                                return;
-                            }
-                            let data = download.unwrap();
-
-                            if let Err(why) = self
-                                .process_chime_data(&data, command.user.id.0, None)
-                                .await
-                            {
-                                info!("Checking chime data for user {username} failed: {:?}", why);
-                                self.respond(&command, ctx, false, Some(format!("{why}").as_str()))
-                                    .await;
-                                return;
-                            }
-
-                            info!("User {username} changed his chime successfully.");
-                            self.respond(&command, ctx, true, None).await;
-                            return;
-                        } // url
-                        _ => warn!("Malformed command received {:?}", base_option),
-                    }; // match attachment, url
-                } // "set"
-                "admin" => {
-                    if command.guild_id.is_none() {
-                        self.respond(&command, ctx, false, Some("only-in-guilds"))
-                            .await;
-                        return;
-                    }
+    async fn fetch(
+        resolvers: &[Box<dyn resolve::MediaResolver>],
+        policy: &download::DownloadPolicy,
+        url: &str,
+        dest: &Path,
+    ) -> Result<(), CommandError> {
+        fetch_url(
+            resolvers,
+            policy,
+            DURATION_MAX,
+            url::Url::parse(url).unwrap(),
+            dest,
+            Some(5),
+        )
+        .await
+    }
 
-                    let invoking_member = command
-                        .guild_id
-                        .unwrap()
-                        .member(&ctx.http, command.user.id)
-                        .await;
+    #[tokio::test]
+    async fn fetch_url_routes_to_resolver() {
+        // leftovers of previous runs are removed
+        let dir = TempDir::new(std::env::temp_dir().join("dab-rs-fetch-test")).unwrap();
+        let (calls, resolvers) = stub(false);
+        let policy = local_policy();
+
+        for url in [
+            "http://localhost/watch?t=1m30s",
+            "http://localhost/watch?t=5&end=8",
+            "http://localhost/watch",
+        ] {
+            let dest = dir.file();
+            fetch(&resolvers, &policy, url, dest.path()).await.unwrap();
+            assert_eq!(std::fs::read(dest.path()).unwrap(), b"stub");
+        }
 
-                    if let Err(why) = invoking_member {
-                        error!("Could not get invoking member: {why:?}");
-                        self.respond(&command, ctx, false, None).await;
-                        return;
-                    }
-                    let invoking_member = invoking_member.unwrap();
+        // ranges without an end last as long as a chime may
+        let range = |start, end| {
+            Some(resolve::TimeRange {
+                start: Duration::from_secs(start),
+                end: Some(Duration::from_secs(end)),
+            })
+        };
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                ("http://localhost/watch?t=1m30s".to_string(), range(90, 100)),
+                ("http://localhost/watch?t=5&end=8".to_string(), range(5, 8)),
+                ("http://localhost/watch".to_string(), None),
+            ]
+        );
+    }
 
-                    let permissions = invoking_member.permissions(&ctx);
-                    if let Err(why) = permissions {
-                        error!("Could not get permissions of user '{username}': {why:?}");
-                        self.respond(&command, ctx, false, None).await;
-                        return;
-                    }
-                    let perm = permissions.unwrap();
+    #[tokio::test]
+    async fn fetch_url_checks_policy_before_resolving() {
+        let dir = TempDir::new(std::env::temp_dir().join("dab-rs-fetch-policy-test")).unwrap();
+        let (calls, resolvers) = stub(false);
 
-                    if !(perm.administrator() || perm.manage_guild()) {
-                        warn!(
-                            "User '{username}' tried to execute admin command without permissions."
-                        );
-                        self.respond(&command, ctx, false, Some("missing-permissions"))
-                            .await;
-                        return;
-                    }
+        let policy = download::DownloadPolicy {
+            denied_domains: download::DownloadPolicy::parse_domains("localhost"),
+            ..local_policy()
+        };
+        let dest = dir.file();
+        assert!(matches!(
+            fetch(&resolvers, &policy, "http://localhost/watch", dest.path()).await,
+            Err(CommandError::Download(download::DownloadError::Domain))
+        ));
+
+        // urls the resolver does not handle are downloaded directly
+        assert!(matches!(
+            fetch(
+                &resolvers,
+                &download::DownloadPolicy::default(),
+                "http://127.0.0.1/chime.mp3",
+                dest.path()
+            )
+            .await,
+            Err(CommandError::Download(download::DownloadError::Address(_)))
+        ));
+
+        assert!(calls.lock().unwrap().is_empty());
+        assert!(!dest.path().exists());
+    }
 
-                    let admin_option = &base_option // "admin"
-                        .options
-                        .get(0) // forbid
-                        .unwrap();
-
-                    match admin_option.name.as_str() {
-                        "forbid" => {
-                            if let Some(CommandDataOptionValue::Role(role)) =
-                                &admin_option.options.get(0).unwrap().resolved
-                            {
-                                let guild_id = role.guild_id.as_u64();
-
-                                let mut guild_details = self
-                                    .database
-                                    .get_guild_details(guild_id)
-                                    .await
-                                    .unwrap_or_default();
-
-                                guild_details.id = *role.guild_id.as_u64();
-                                guild_details.blocked_role_id = Some(*role.id.as_u64());
-
-                                if let Err(why) =
-                                    self.database.set_guild_details(guild_details).await
-                                {
-                                    error!("Could not set blocked role '{role}' for guild '{guild_id}': {why:?}");
-                                    self.respond(&command, ctx, false, Some("internal-error"))
-                                        .await;
-                                } else {
-                                    info!("User '{username}' changed blocked role for guild '{guild_id}' to '{role}'");
-                                    self.respond(&command, ctx, true, None).await;
-                                }
-                            }
-                        }
-                        _ => warn!("Malformed admin-command received {:?}", admin_option),
-                    };
-                }
-                val => warn!("Unknown option received! {}", val),
-            }; // match name
-        } // if let interaction
+    #[tokio::test]
+    async fn fetch_url_reports_limit_of_resolver() {
+        let dir = TempDir::new(std::env::temp_dir().join("dab-rs-fetch-limit-test")).unwrap();
+        let (calls, resolvers) = stub(true);
+
+        let dest = dir.file();
+        assert!(matches!(
+            fetch(
+                &resolvers,
+                &local_policy(),
+                "http://localhost/watch",
+                dest.path()
+            )
+            .await,
+            Err(CommandError::FileTooLarge {
+                size_bytes: None,
+                limit_bytes: 5
+            })
+        ));
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
+
+    fn attachment(filename: &str, content_type: Option<&str>) -> Attachment {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "filename": filename,
+            "size": 1000,
+            "url": "https://cdn.discordapp.com/attachments/1/1/chime",
+            "proxy_url": "https://media.discordapp.net/attachments/1/1/chime",
+            "content_type": content_type,
+        }))
+        .unwrap()
+    }
 
-        // borrowing issues
-        // _ = self.latest_context.lock().await.insert(ctx);
+    #[test]
+    fn audio_attachments() {
+        assert!(is_audio(&attachment("chime.bin", Some("audio/mpeg"))));
+        assert!(!is_audio(&attachment("chime.mp3", Some("image/png"))));
+        assert!(is_audio(&attachment("chime.MP3", None)));
+        assert!(is_audio(&attachment("chime.ogg", None)));
+        assert!(!is_audio(&attachment("chime.txt", None)));
+        assert!(!is_audio(&attachment("chime", None)));
     }
 }
@@ -0,0 +1,151 @@
+use crate::{chimes, data};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use log::{error, info};
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+#[derive(Default)]
+pub struct HealthState {
+    gateway_connected: AtomicBool,
+    ready: AtomicBool,
+    commands_registered: AtomicBool,
+    cleanup_watcher_beat: Mutex<Option<Instant>>,
+}
+impl HealthState {
+    pub fn set_gateway_connected(&self, connected: bool) {
+        self.gateway_connected.store(connected, Ordering::Relaxed);
+    }
+
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_commands_registered(&self) {
+        self.commands_registered.store(true, Ordering::Relaxed);
+    }
+
+    pub async fn cleanup_watcher_beat(&self) {
+        _ = self
+            .cleanup_watcher_beat
+            .lock()
+            .await
+            .insert(Instant::now());
+    }
+}
+
+pub struct HealthServer {
+    pub state: Arc<HealthState>,
+    pub database: Arc<data::DatabaseInterface>,
+    pub sink: Arc<dyn chimes::ChimeSink>,
+    /// maximum age of the last beat of the cleanup watcher until it is considered dead
+    pub watcher_timeout: Duration,
+}
+impl HealthServer {
+    async fn cleanup_watcher_alive(&self) -> Option<bool> {
+        self.state
+            .cleanup_watcher_beat
+            .lock()
+            .await
+            .map(|beat| beat.elapsed() <= self.watcher_timeout)
+    }
+
+    async fn liveness(&self) -> (StatusCode, String) {
+        // watcher is not spawned before ready, which does not make the process unhealthy
+        let watcher_alive = self.cleanup_watcher_alive().await;
+
+        let status = if watcher_alive.unwrap_or(true) {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+        (
+            status,
+            serde_json::json!({
+                "status": status.as_u16(),
+                "cleanup_watcher": watcher_alive,
+            })
+            .to_string(),
+        )
+    }
+
+    async fn readiness(&self) -> (StatusCode, String) {
+        let gateway = self.state.gateway_connected.load(Ordering::Relaxed);
+        let ready = self.state.ready.load(Ordering::Relaxed);
+        let commands = self.state.commands_registered.load(Ordering::Relaxed);
+        let database = self.database.is_reachable().await;
+        let sink = self.sink.is_available().await;
+        let watcher = self.cleanup_watcher_alive().await.unwrap_or(false);
+
+        let status = if gateway && ready && commands && database && sink && watcher {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+        (
+            status,
+            serde_json::json!({
+                "status": status.as_u16(),
+                "gateway_connected": gateway,
+                "ready": ready,
+                "commands_registered": commands,
+                "database_reachable": database,
+                "sink_available": sink,
+                "cleanup_watcher": watcher,
+            })
+            .to_string(),
+        )
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let (status, body) = match (request.method(), request.uri().path()) {
+            (&Method::GET, "/health/live") => self.liveness().await,
+            (&Method::GET, "/health/ready") => self.readiness().await,
+            _ => (StatusCode::NOT_FOUND, String::new()),
+        };
+
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = status;
+        response
+            .headers_mut()
+            .insert("content-type", "application/json".parse().unwrap());
+        response
+    }
+
+    pub async fn serve(self, addr: SocketAddr) {
+        let server = Arc::new(self);
+
+        let make_service = make_service_fn(move |_| {
+            let server = Arc::clone(&server);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = Arc::clone(&server);
+                    async move { Ok::<_, Infallible>(server.handle(request).await) }
+                }))
+            }
+        });
+
+        match Server::try_bind(&addr) {
+            Ok(builder) => {
+                info!("Serving health endpoints on {addr}");
+                if let Err(why) = builder.serve(make_service).await {
+                    error!("Health server failed: {why:?}");
+                }
+            }
+            Err(why) => error!("Could not bind health server to {addr}: {why:?}"),
+        }
+    }
+}
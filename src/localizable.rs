@@ -1,6 +1,7 @@
 pub trait Localizable {
     fn localize_default(&mut self, _localization: &str) -> &mut Self;
     fn localize(&mut self, locale: &str, localization: &str) -> &mut Self;
+    fn localize_name(&mut self, locale: &str, name: &str) -> &mut Self;
 }
 impl Localizable for serenity::builder::CreateApplicationCommandOption {
     fn localize_default(&mut self, localization: &str) -> &mut Self {
@@ -10,6 +11,10 @@ impl Localizable for serenity::builder::CreateApplicationCommandOption {
     fn localize(&mut self, locale: &str, localization: &str) -> &mut Self {
         self.description_localized(locale, localization)
     }
+
+    fn localize_name(&mut self, locale: &str, name: &str) -> &mut Self {
+        self.name_localized(locale, name)
+    }
 }
 impl Localizable for serenity::builder::CreateApplicationCommand {
     fn localize_default(&mut self, localization: &str) -> &mut Self {
@@ -19,4 +24,8 @@ impl Localizable for serenity::builder::CreateApplicationCommand {
     fn localize(&mut self, locale: &str, localization: &str) -> &mut Self {
         self.description_localized(locale, localization)
     }
+
+    fn localize_name(&mut self, locale: &str, name: &str) -> &mut Self {
+        self.name_localized(locale, name)
+    }
 }
@@ -1,15 +1,30 @@
 extern crate getopts;
+mod archive;
 mod chimes;
+mod cli;
+mod commands;
+mod cooldown;
 mod data;
+mod download;
+mod error;
 mod fluent;
 mod handler;
+mod health;
 mod localizable;
+mod messages;
+mod moderation;
 mod nameable;
+mod ratelimit;
+mod registration;
+mod resolve;
+mod response;
+mod tempfiles;
+mod validation;
 
 use chrono::prelude::*;
 use config::Config;
 use getopts::Options;
-use log::error;
+use log::{error, warn};
 use serenity::prelude::*;
 use songbird::SerenityInit;
 use std::{collections::HashMap, env, sync::Arc, time::Duration};
@@ -66,56 +81,29 @@ fn setup_logger(path: &str, verbose: bool, heartbeat: bool) -> Result<(), fern::
     Ok(())
 }
 
-#[tokio::main]
-async fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    let mut opts = Options::new();
-    opts.optopt("c", "config", "Path to configuration", "FILE");
-    opts.optflag("v", "verbose", "Verbose logging in stdout");
-    opts.optflag("b", "beats", "Heartbeat logging in stdout");
-    let opts = opts.parse(&args[1..]).expect("Bad arguments!");
-
-    let settings = Config::builder()
-        .add_source(config::File::with_name(
-            &opts
-                .opt_get_default("c", String::from("Settings.toml"))
-                .unwrap(),
-        ))
-        .build()
-        .expect("Could not build settings!")
-        .try_deserialize::<HashMap<String, String>>()
-        .expect("Could not deserialize settings!");
-
-    setup_logger(
-        &settings["LOG_PATH"],
-        opts.opt_present("v"),
-        opts.opt_present("b"),
-    )
-    .expect("Could not setup logger!");
-
-    let intents = GatewayIntents::non_privileged()
-        | GatewayIntents::MESSAGE_CONTENT
-        | GatewayIntents::GUILD_VOICE_STATES;
-
+async fn create_sink(settings: &HashMap<String, String>) -> chimes::FileChimeSink {
     let mut userdata_dir = std::path::PathBuf::new();
     userdata_dir.push(settings["USERDATA_DIR"].as_str());
 
-    let sink = chimes::FileChimeSink::new(userdata_dir)
+    chimes::FileChimeSink::new(userdata_dir)
         .await
-        .expect("Could not initialize sink!");
+        .expect("Could not initialize sink!")
+}
 
+fn create_localizer(settings: &HashMap<String, String>) -> fluent::FluentLocalizer {
     let mut resource_dir = std::path::PathBuf::new();
     resource_dir.push(settings["RESOURCE_DIR"].as_str());
 
-    let localizer = fluent::FluentLocalizer::new(
+    fluent::FluentLocalizer::new(
         settings["DEFAULT_LOCALE"]
             .parse::<LanguageIdentifier>()
             .expect("Could not parse default locale!"),
         resource_dir,
     )
-    .expect("Could not initialize localizer!");
+    .expect("Could not initialize localizer!")
+}
 
+async fn connect_database(settings: &HashMap<String, String>) -> data::DatabaseInterface {
     let mut config = tokio_postgres::config::Config::new();
     config
         .host(settings["DB_HOSTNAME"].as_str())
@@ -138,13 +126,240 @@ async fn main() {
 
     database_interface.ensure_table_exists().await;
 
+    database_interface
+}
+
+fn validation_policy(settings: &HashMap<String, String>) -> validation::ValidationPolicy {
+    let mut policy = validation::ValidationPolicy::new(Duration::from_millis(
+        settings["CHIME_DURATION_MAX_MS"]
+            .as_str()
+            .parse::<u64>()
+            .expect("Could not get file-duration-max from config"),
+    ));
+    if let Some(codecs) = settings.get("CHIME_ALLOWED_CODECS") {
+        policy.allowed_codecs = codecs
+            .split(',')
+            .map(|codec| codec.trim().to_lowercase())
+            .filter(|codec| !codec.is_empty())
+            .collect();
+    }
+    if let Some(channels) = settings.get("CHIME_CHANNELS_MAX") {
+        policy.channels_max = channels
+            .parse::<i64>()
+            .expect("Could not get maximum channels from config");
+    }
+    if let Some(rate) = settings.get("CHIME_SAMPLE_RATE_MIN") {
+        policy.sample_rates = rate
+            .parse::<u32>()
+            .expect("Could not get minimum sample rate from config")
+            ..=*policy.sample_rates.end();
+    }
+    if let Some(rate) = settings.get("CHIME_SAMPLE_RATE_MAX") {
+        policy.sample_rates = *policy.sample_rates.start()
+            ..=rate
+                .parse::<u32>()
+                .expect("Could not get maximum sample rate from config");
+    }
+    if let Some(video) = settings.get("CHIME_VIDEO") {
+        policy.video = video
+            .parse()
+            .expect("Could not get video policy from config");
+    }
+    if let Some(threshold) = settings.get("CHIME_SILENCE_THRESHOLD_DB") {
+        policy.silence_threshold_db = match threshold.as_str() {
+            "none" => None,
+            threshold => Some(
+                threshold
+                    .parse::<f64>()
+                    .expect("Could not get silence threshold from config"),
+            ),
+        };
+    }
+    policy
+}
+
+fn command_scope(settings: &HashMap<String, String>) -> registration::CommandScope {
+    settings
+        .get("COMMAND_GUILDS")
+        .map_or(registration::CommandScope::Global, |guilds| {
+            guilds
+                .parse()
+                .expect("Could not get command guilds from config")
+        })
+}
+
+/// Where files in flight are kept, cleared of leftovers
+/// Holds the temporary directory of the bot in `bot` and those of offline commands in `cli`
+fn temp_root(settings: &HashMap<String, String>) -> std::path::PathBuf {
+    settings
+        .get("TEMP_DIR")
+        .map_or_else(|| std::env::temp_dir().join("dab-rs"), Into::into)
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut options = Options::new();
+    options.optopt("c", "config", "Path to configuration", "FILE");
+    options.optflag("v", "verbose", "Verbose logging in stdout");
+    options.optflag("b", "beats", "Heartbeat logging in stdout");
+    options.optflag(
+        "",
+        "check-localization",
+        "Check localizations against the messages used and exit",
+    );
+    let opts = options.parse(&args[1..]).expect("Bad arguments!");
+
+    let settings = Config::builder()
+        .add_source(config::File::with_name(
+            &opts
+                .opt_get_default("c", String::from("Settings.toml"))
+                .unwrap(),
+        ))
+        .build()
+        .expect("Could not build settings!")
+        .try_deserialize::<HashMap<String, String>>()
+        .expect("Could not deserialize settings!");
+
+    setup_logger(
+        &settings["LOG_PATH"],
+        opts.opt_present("v"),
+        opts.opt_present("b"),
+    )
+    .expect("Could not setup logger!");
+
+    if opts.opt_present("check-localization") {
+        let report = create_localizer(&settings).check(messages::MESSAGES);
+        if !report.is_empty() {
+            eprint!("{report}");
+            std::process::exit(1);
+        }
+        println!("All localizations are complete");
+        return;
+    }
+
+    if !opts.free.is_empty() {
+        if let Err(why) = cli::run(&opts.free, &settings).await {
+            eprintln!("{why}\n\n{}", options.usage(cli::USAGE));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let intents = GatewayIntents::non_privileged()
+        | GatewayIntents::MESSAGE_CONTENT
+        | GatewayIntents::GUILD_VOICE_STATES;
+
+    let sink = create_sink(&settings).await;
+
+    let localizer = create_localizer(&settings);
+
+    let report = localizer.check(messages::MESSAGES);
+    if !report.is_empty() {
+        if settings
+            .get("STRICT_LOCALIZATION")
+            .is_some_and(|strict| strict == "true")
+        {
+            error!("Localizations are incomplete:\n{report}");
+            std::process::exit(1);
+        }
+        warn!("Localizations are incomplete:\n{report}");
+    }
+
+    let database_interface = Arc::new(connect_database(&settings).await);
     let sink = Arc::new(sink);
+    let health = Arc::new(health::HealthState::default());
+
+    let disconnect_timeout = Duration::from_millis(
+        settings["CONNECTION_TIMEOUT_MILLISECONDS"]
+            .as_str()
+            .parse::<u64>()
+            .expect("Could not get connection-timeout-ms from config"),
+    );
 
-    let handler = handler::HandlerBuilder::default()
+    if let Some(addr) = settings.get("HEALTH_BIND_ADDRESS") {
+        let server = health::HealthServer {
+            state: Arc::clone(&health),
+            database: Arc::clone(&database_interface),
+            sink: sink.clone(),
+            // cleanup watcher beats once per timeout
+            watcher_timeout: 2 * disconnect_timeout + Duration::from_secs(5),
+        };
+        tokio::spawn(
+            server.serve(
+                addr.parse::<std::net::SocketAddr>()
+                    .expect("Could not parse health bind address from config"),
+            ),
+        );
+    }
+
+    let mut download_policy = download::DownloadPolicy::default();
+    if let Some(max_redirects) = settings.get("DOWNLOAD_MAX_REDIRECTS") {
+        download_policy.max_redirects = max_redirects
+            .parse::<usize>()
+            .expect("Could not get maximum redirects from config");
+    }
+    if let Some(domains) = settings.get("DOWNLOAD_ALLOWED_DOMAINS") {
+        download_policy.allowed_domains = download::DownloadPolicy::parse_domains(domains);
+    }
+    if let Some(domains) = settings.get("DOWNLOAD_DENIED_DOMAINS") {
+        download_policy.denied_domains = download::DownloadPolicy::parse_domains(domains);
+    }
+    if let Some(allow) = settings.get("DOWNLOAD_ALLOW_PRIVATE_ADDRESSES") {
+        download_policy.allow_private_addresses = allow
+            .parse::<bool>()
+            .expect("Could not get private address policy from config");
+    }
+    if let Some(timeout) = settings.get("DOWNLOAD_CONNECT_TIMEOUT_MS") {
+        download_policy.connect_timeout = Duration::from_millis(
+            timeout
+                .parse::<u64>()
+                .expect("Could not get download connect-timeout-ms from config"),
+        );
+    }
+    if let Some(timeout) = settings.get("DOWNLOAD_READ_TIMEOUT_MS") {
+        download_policy.read_timeout = Duration::from_millis(
+            timeout
+                .parse::<u64>()
+                .expect("Could not get download read-timeout-ms from config"),
+        );
+    }
+
+    let temp_dir = tempfiles::TempDir::new(temp_root(&settings).join("bot"))
+        .expect("Could not prepare temporary directory!");
+
+    let mut cooldown_limits = cooldown::CooldownLimits::default();
+    if let Some(seconds) = settings.get("CHIME_USER_COOLDOWN_SECONDS") {
+        cooldown_limits.user_cooldown = Duration::from_secs(
+            seconds
+                .parse::<u64>()
+                .expect("Could not get user cooldown from config"),
+        );
+    }
+    if let Some(plays) = settings.get("CHIME_GUILD_PLAYS_PER_MINUTE") {
+        cooldown_limits.guild_plays_per_minute = plays
+            .parse::<u64>()
+            .expect("Could not get guild plays per minute from config");
+    }
+
+    let builder = handler::HandlerBuilder::default()
         .command_root(&settings["COMMAND_ROOT"])
+        .command_scope(command_scope(&settings))
         .localizer(localizer)
         .database(database_interface)
         .sink(sink)
+        .health(health)
+        .download_policy(download_policy)
+        .temp_dir(temp_dir)
+        .cooldown_limits(cooldown_limits)
+        .rate_limiter(match settings.get("COMMAND_RATE_LIMITS") {
+            Some(limits) => ratelimit::CommandRateLimiter::new(
+                ratelimit::CommandRateLimiter::parse_limits(limits)
+                    .expect("Could not get command rate limits from config"),
+            ),
+            None => ratelimit::CommandRateLimiter::default(),
+        })
         .bus_size(
             settings["BUS_SIZE"]
                 .as_str()
@@ -157,19 +372,29 @@ async fn main() {
                 .parse::<isize>()
                 .expect("Could not get maximum filesize from config"),
         )
-        .file_duration_max(Duration::from_millis(
-            settings["CHIME_DURATION_MAX_MS"]
-                .as_str()
-                .parse::<u64>()
-                .expect("Could not get file-duration-max from config"),
-        ))
-        .disconnect_timeout(Duration::from_millis(
-            settings["CONNECTION_TIMEOUT_MILLISECONDS"]
-                .as_str()
-                .parse::<u64>()
-                .expect("Could not get connection-timeout-ms from config"),
-        ))
-        .build();
+        .validation_policy(validation_policy(&settings))
+        .disconnect_timeout(disconnect_timeout);
+
+    #[cfg(feature = "yt-dlp")]
+    let builder = {
+        let mut resolver = resolve::YtDlpResolver::default();
+        if let Some(binary) = settings.get("YT_DLP_PATH") {
+            resolver.binary = binary.into();
+        }
+        if let Some(domains) = settings.get("YT_DLP_DOMAINS") {
+            resolver.domains = download::DownloadPolicy::parse_domains(domains);
+        }
+        if let Some(timeout) = settings.get("YT_DLP_TIMEOUT_MS") {
+            resolver.timeout = Duration::from_millis(
+                timeout
+                    .parse::<u64>()
+                    .expect("Could not get yt-dlp timeout-ms from config"),
+            );
+        }
+        builder.resolver(resolver)
+    };
+
+    let handler = builder.build();
 
     let mut client = Client::builder(settings["API_TOKEN"].as_str(), intents)
         .event_handler(handler)
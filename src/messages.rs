@@ -0,0 +1,93 @@
+/// Ids of all messages localized by the code, along with the variables passed to them
+pub const MESSAGES: &[(&str, &[&str])] = &[
+    ("success", &[]),
+    ("command-outdated", &[]),
+    ("file-too-large", &["size", "limit"]),
+    ("download-failed", &[]),
+    ("bad-url", &[]),
+    ("duration-exceeded", &["duration", "limit"]),
+    ("data-unreadable", &[]),
+    ("internal-error", &[]),
+    ("only-in-guilds", &[]),
+    ("missing-permissions", &[]),
+    ("privacy-export-message", &[]),
+    ("privacy-export-sent", &[]),
+    ("privacy-export-dm-failed", &[]),
+    ("privacy-delete-confirm", &[]),
+    ("privacy-delete-button", &[]),
+    ("privacy-delete-done", &[]),
+    ("url-forbidden", &[]),
+    ("resolve-failed", &[]),
+    ("no-audio-stream", &[]),
+    ("unsupported-codec", &["codec", "allowed"]),
+    ("unsupported-channels", &["channels", "max"]),
+    ("unsupported-sample-rate", &["rate", "min", "max"]),
+    ("video-not-allowed", &[]),
+    ("chime-silent", &[]),
+    ("chime-pending-approval", &[]),
+    ("chime-still-pending", &[]),
+    ("chime-rejected", &[]),
+    ("moderation-enabled", &["channel"]),
+    ("moderation-disabled", &[]),
+    ("moderation-review", &["user"]),
+    ("moderation-approve", &[]),
+    ("moderation-reject", &[]),
+    ("moderation-approved", &["moderator"]),
+    ("moderation-rejected", &["moderator"]),
+    ("moderation-already-decided", &[]),
+    ("role-forbidden", &["role"]),
+    ("cooldowns-set", &["user", "guild"]),
+    ("user-muted", &["user"]),
+    ("user-unmuted", &["user"]),
+    ("user-chime-cleared", &["user"]),
+    ("user-has-no-chime", &["user"]),
+    ("rate-limited", &["retry"]),
+    ("no-audio-attachment", &[]),
+    (
+        "audit-entry-change",
+        &["time", "actor", "action", "old", "new"],
+    ),
+    (
+        "audit-entry-user",
+        &["time", "actor", "action", "target", "old", "new"],
+    ),
+    ("audit-empty", &[]),
+    ("locale-name", &[]),
+    ("language-set", &["language"]),
+    ("language-reset", &[]),
+    // descriptions of commands and their options
+    ("base", &[]),
+    ("base-clear", &[]),
+    ("base-set", &[]),
+    ("base-set-file", &[]),
+    ("base-set-file-attachment", &[]),
+    ("base-set-url", &[]),
+    ("base-set-url-link", &[]),
+    ("base-admin", &[]),
+    ("base-admin-forbid", &[]),
+    ("base-admin-forbid-role", &[]),
+    ("base-privacy", &[]),
+    ("base-privacy-export", &[]),
+    ("base-privacy-delete", &[]),
+    ("base-admin-moderation", &[]),
+    ("base-admin-moderation-channel", &[]),
+    ("base-admin-mute", &[]),
+    ("base-admin-mute-user", &[]),
+    ("base-admin-unmute", &[]),
+    ("base-admin-unmute-user", &[]),
+    ("base-admin-clear", &[]),
+    ("base-admin-clear-user", &[]),
+    ("base-admin-cooldown", &[]),
+    ("base-admin-cooldown-user", &[]),
+    ("base-admin-cooldown-guild", &[]),
+    ("base-admin-log-channel", &[]),
+    ("base-admin-log-channel-channel", &[]),
+    ("base-admin-audit", &[]),
+    ("base-admin-audit-limit", &[]),
+    ("base-language", &[]),
+    ("base-language-locale", &[]),
+    ("base-admin-language", &[]),
+    ("base-admin-language-locale", &[]),
+    // names of commands in context menus
+    ("message-use-as-chime", &[]),
+];
@@ -0,0 +1,99 @@
+use crate::{
+    chimes::{ChimeSink, ChimeSinkError},
+    data::{DatabaseInterface, ReviewStatus},
+};
+
+use async_trait::async_trait;
+use songbird::input::Input;
+use std::{path::PathBuf, sync::Arc};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Approval {
+    /// guild does not moderate chimes
+    NotRequired,
+    Approved,
+    Pending,
+    Rejected,
+    /// current chime was not submitted to the guild yet
+    Unsubmitted,
+}
+
+/// Plays chimes in guilds with moderation only after their moderators approved them
+pub struct ModeratedChimeSink {
+    inner: Arc<dyn ChimeSink>,
+    database: Arc<DatabaseInterface>,
+}
+impl ModeratedChimeSink {
+    pub fn new(inner: Arc<dyn ChimeSink>, database: Arc<DatabaseInterface>) -> Self {
+        ModeratedChimeSink { inner, database }
+    }
+
+    /// Channel where chimes are reviewed, if guild moderates chimes
+    pub async fn moderation_channel(&self, guild_id: u64) -> Option<u64> {
+        self.database
+            .get_guild_details(&guild_id)
+            .await
+            .and_then(|details| details.moderation_channel_id)
+    }
+
+    pub async fn approval(&self, guild_id: u64, user_id: u64) -> Approval {
+        if self.moderation_channel(guild_id).await.is_none() {
+            return Approval::NotRequired;
+        }
+
+        let chime_id = match self.inner.get_chime_id(user_id).await {
+            Some(id) => id,
+            None => return Approval::Unsubmitted,
+        };
+
+        match self
+            .database
+            .get_review_status(guild_id, user_id, &chime_id)
+            .await
+        {
+            Some(ReviewStatus::Approved) => Approval::Approved,
+            Some(ReviewStatus::Pending) => Approval::Pending,
+            Some(ReviewStatus::Rejected) => Approval::Rejected,
+            None => Approval::Unsubmitted,
+        }
+    }
+}
+#[async_trait]
+impl ChimeSink for ModeratedChimeSink {
+    async fn has_data(&self, user_id: u64) -> bool {
+        self.inner.has_data(user_id).await
+    }
+
+    async fn get_chime_id(&self, user_id: u64) -> Option<String> {
+        self.inner.get_chime_id(user_id).await
+    }
+
+    async fn get_input(&self, user_id: u64, guild_id: u64) -> Result<Input, ChimeSinkError> {
+        match self.approval(guild_id, user_id).await {
+            Approval::NotRequired | Approval::Approved => {
+                self.inner.get_input(user_id, guild_id).await
+            }
+            _ => Err(ChimeSinkError::NotApproved),
+        }
+    }
+
+    async fn save_data(&self, user_id: u64, file: PathBuf) -> Result<(), ChimeSinkError> {
+        self.inner.save_data(user_id, file).await
+    }
+
+    async fn clear_data(&self, user_id: u64) {
+        self.inner.clear_data(user_id).await
+    }
+
+    async fn export_data(&self, user_id: u64, dest: PathBuf) -> Result<(), ChimeSinkError> {
+        self.inner.export_data(user_id, dest).await
+    }
+
+    async fn get_users(&self) -> Vec<u64> {
+        self.inner.get_users().await
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// At most `count` invocations per `period`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub count: usize,
+    pub period: Duration,
+}
+impl FromStr for RateLimit {
+    type Err = String;
+
+    /// Parses `COUNT/SECONDS`, e.g. `3/60`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, seconds) = s
+            .split_once('/')
+            .ok_or_else(|| format!("Rate limit '{s}' is not COUNT/SECONDS"))?;
+        Ok(RateLimit {
+            count: count
+                .trim()
+                .parse()
+                .map_err(|why| format!("Invalid count in rate limit '{s}': {why}"))?,
+            period: Duration::from_secs(
+                seconds
+                    .trim()
+                    .parse()
+                    .map_err(|why| format!("Invalid seconds in rate limit '{s}': {why}"))?,
+            ),
+        })
+    }
+}
+
+/// Limits how often each user may invoke a subcommand, e.g. `set url`
+pub struct CommandRateLimiter {
+    limits: HashMap<String, RateLimit>,
+    invocations: Mutex<HashMap<(u64, String), VecDeque<Instant>>>,
+}
+impl Default for CommandRateLimiter {
+    fn default() -> Self {
+        Self::new(
+            Self::parse_limits(
+                "set url=3/60, set file=5/60, Use as my chime=5/60, privacy export=2/3600",
+            )
+            .expect("Bad default rate limits"),
+        )
+    }
+}
+impl CommandRateLimiter {
+    pub fn new(limits: HashMap<String, RateLimit>) -> Self {
+        CommandRateLimiter {
+            limits,
+            invocations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Parses comma-separated `SUBCOMMAND=COUNT/SECONDS`, e.g. `set url=3/60, clear=10/60`
+    pub fn parse_limits(limits: &str) -> Result<HashMap<String, RateLimit>, String> {
+        limits
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (command, limit) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("Rate limit '{entry}' is not SUBCOMMAND=LIMIT"))?;
+                Ok((
+                    command.split_whitespace().collect::<Vec<_>>().join(" "),
+                    limit.parse()?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Records an invocation, or returns how long user has to wait if limited
+    pub async fn check(&self, user_id: u64, command: &str) -> Result<(), Duration> {
+        let limit = match self.limits.get(command) {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        let now = Instant::now();
+        let mut invocations = self.invocations.lock().await;
+
+        for (key, times) in invocations.iter_mut() {
+            let period = self.limits.get(&key.1).map_or(Duration::ZERO, |l| l.period);
+            while times
+                .front()
+                .is_some_and(|time| now.duration_since(*time) >= period)
+            {
+                times.pop_front();
+            }
+        }
+        invocations.retain(|_, times| !times.is_empty());
+
+        let times = invocations
+            .entry((user_id, command.to_string()))
+            .or_default();
+
+        if times.len() >= limit.count {
+            // earliest invocation leaves the window first
+            return Err(times.front().map_or(limit.period, |first| {
+                limit.period.saturating_sub(now.duration_since(*first))
+            }));
+        }
+
+        times.push_back(now);
+        Ok(())
+    }
+}
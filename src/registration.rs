@@ -0,0 +1,257 @@
+use log::info;
+use serde_json::{json, Map, Value};
+use serenity::{
+    http::{GuildPagination, Http},
+    model::{application::command::Command, id::GuildId},
+};
+use std::str::FromStr;
+
+/// Where the application commands of the bot are registered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandScope {
+    /// in all guilds, which may take a while to propagate
+    Global,
+    /// only in these guilds, which takes effect immediately
+    Guilds(Vec<u64>),
+}
+impl FromStr for CommandScope {
+    type Err = String;
+
+    /// Parses comma-separated guild ids, global if there are none
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let guilds = s
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.parse::<u64>()
+                    .map_err(|why| format!("Invalid guild id '{id}': {why}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(match guilds.is_empty() {
+            true => CommandScope::Global,
+            false => CommandScope::Guilds(guilds),
+        })
+    }
+}
+
+/// Fields of a command or option that discord keeps, with the defaults it omits filled in
+fn canonical(command: &Value) -> Value {
+    let field = |name: &str, default: Value| match command.get(name) {
+        Some(Value::Null) | None => default,
+        Some(value) => value.clone(),
+    };
+
+    let options = field("options", json!([]))
+        .as_array()
+        .map(|options| options.iter().map(canonical).collect())
+        .unwrap_or_default();
+    let choices = field("choices", json!([]))
+        .as_array()
+        .map(|choices| {
+            choices
+                .iter()
+                .map(|choice| {
+                    json!({
+                        "name": choice["name"],
+                        "name_localizations": match &choice["name_localizations"] {
+                            Value::Null => json!({}),
+                            localizations => localizations.clone(),
+                        },
+                        "value": choice["value"],
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut canonical = Map::new();
+    for (name, default) in [
+        ("type", json!(1)),
+        ("name", json!("")),
+        ("name_localizations", json!({})),
+        ("description", json!("")),
+        ("description_localizations", json!({})),
+        ("required", json!(false)),
+        ("channel_types", json!([])),
+        ("min_value", Value::Null),
+        ("max_value", Value::Null),
+        ("autocomplete", json!(false)),
+    ] {
+        canonical.insert(name.to_string(), field(name, default));
+    }
+    canonical.insert("choices".to_string(), Value::Array(choices));
+    canonical.insert("options".to_string(), Value::Array(options));
+    Value::Object(canonical)
+}
+
+/// Whether registering commands would leave existing ones as they are
+fn unchanged(existing: &[Command], commands: &[Value]) -> bool {
+    let mut existing = existing
+        .iter()
+        .map(|command| canonical(&serde_json::to_value(command).unwrap_or_default()))
+        .collect::<Vec<_>>();
+    let mut commands = commands.iter().map(canonical).collect::<Vec<_>>();
+
+    let by_name = |a: &Value, b: &Value| a["name"].as_str().cmp(&b["name"].as_str());
+    existing.sort_by(by_name);
+    commands.sort_by(by_name);
+
+    existing == commands
+}
+
+/// Registers commands in guild, or globally without one, unless they are registered already
+async fn sync(http: &Http, guild_id: Option<u64>, commands: &[Value]) -> serenity::Result<()> {
+    let target = match guild_id {
+        Some(guild_id) => format!("guild {guild_id}"),
+        None => "all guilds".to_string(),
+    };
+
+    let existing = match guild_id {
+        Some(guild_id) => {
+            http.get_guild_application_commands_with_localizations(guild_id)
+                .await?
+        }
+        None => {
+            http.get_global_application_commands_with_localizations()
+                .await?
+        }
+    };
+    if unchanged(&existing, commands) {
+        info!("Commands in {target} are up to date");
+        return Ok(());
+    }
+
+    let commands = Value::Array(commands.to_vec());
+    match guild_id {
+        Some(guild_id) => {
+            http.create_guild_application_commands(guild_id, &commands)
+                .await?
+        }
+        None => http.create_global_application_commands(&commands).await?,
+    };
+    info!("Updated commands in {target}");
+    Ok(())
+}
+
+/// Registers commands in scope, leaving those that are registered like this already alone
+pub async fn register(
+    http: &Http,
+    scope: &CommandScope,
+    commands: &[Value],
+) -> serenity::Result<()> {
+    match scope {
+        CommandScope::Global => sync(http, None, commands).await,
+        CommandScope::Guilds(guild_ids) => {
+            for guild_id in guild_ids {
+                sync(http, Some(*guild_id), commands).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Ids of all guilds the bot is in
+async fn guild_ids(http: &Http) -> serenity::Result<Vec<u64>> {
+    let mut ids = vec![];
+    loop {
+        let after = ids.last().map(|id| GuildPagination::After(GuildId(*id)));
+        let guilds = http.get_guilds(after.as_ref(), Some(100)).await?;
+        if guilds.is_empty() {
+            return Ok(ids);
+        }
+        ids.extend(guilds.iter().map(|guild| guild.id.0));
+    }
+}
+
+/// Removes commands registered outside of scope, e.g. global ones left over from before
+/// registering per guild, returns where they were removed
+pub async fn clear_stale(http: &Http, scope: &CommandScope) -> serenity::Result<Vec<String>> {
+    let mut cleared = vec![];
+
+    if *scope != CommandScope::Global && !http.get_global_application_commands().await?.is_empty() {
+        http.create_global_application_commands(&json!([])).await?;
+        cleared.push("all guilds".to_string());
+    }
+
+    for guild_id in guild_ids(http).await? {
+        if matches!(scope, CommandScope::Guilds(ids) if ids.contains(&guild_id)) {
+            continue;
+        }
+        if !http
+            .get_guild_application_commands(guild_id)
+            .await?
+            .is_empty()
+        {
+            http.create_guild_application_commands(guild_id, &json!([]))
+                .await?;
+            cleared.push(format!("guild {guild_id}"));
+        }
+    }
+
+    Ok(cleared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(name: &str, description_de: &str, options: &[&str]) -> Value {
+        json!({
+            "name": name,
+            "description": format!("Does {name}"),
+            "description_localizations": { "de": description_de },
+            "options": options
+                .iter()
+                .map(|option| json!({
+                    "type": 3,
+                    "name": option,
+                    "description": format!("The {option}"),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// Command as discord returns it after registering value
+    fn registered(value: &Value) -> Command {
+        let mut value = value.clone();
+        let object = value.as_object_mut().unwrap();
+        object.insert("id".to_string(), json!("1"));
+        object.insert("application_id".to_string(), json!("2"));
+        object.insert("version".to_string(), json!("3"));
+        object.insert("type".to_string(), json!(1));
+        object.insert("default_member_permissions".to_string(), Value::Null);
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn unchanged_in_different_order() {
+        let existing = [
+            registered(&command("base", "Basis", &["url"])),
+            registered(&command("other", "Andere", &[])),
+        ];
+        let commands = [
+            command("other", "Andere", &[]),
+            command("base", "Basis", &["url"]),
+        ];
+
+        assert!(unchanged(&existing, &commands));
+    }
+
+    #[test]
+    fn changed_localization() {
+        let existing = [registered(&command("base", "Basis", &["url"]))];
+        let commands = [command("base", "Grundlage", &["url"])];
+
+        assert!(!unchanged(&existing, &commands));
+    }
+
+    #[test]
+    fn removed_option() {
+        let existing = [registered(&command("base", "Basis", &["url", "file"]))];
+        let commands = [command("base", "Basis", &["url"])];
+
+        assert!(!unchanged(&existing, &commands));
+    }
+}
@@ -0,0 +1,333 @@
+use async_trait::async_trait;
+use std::{error::Error, fmt::Display, path::Path, time::Duration};
+use url::Url;
+
+#[derive(Debug)]
+#[cfg_attr(not(feature = "yt-dlp"), allow(dead_code))]
+pub enum ResolveError {
+    /// page has no audio or could not be fetched
+    Unavailable(String),
+    TooLarge,
+    Timeout,
+    Io(std::io::Error),
+}
+impl Error for ResolveError {}
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::Unavailable(why) => write!(f, "no audio available: {why}"),
+            ResolveError::TooLarge => write!(f, "file too large"),
+            ResolveError::Timeout => write!(f, "timed out"),
+            ResolveError::Io(why) => write!(f, "{why}"),
+        }
+    }
+}
+
+/// Section of the media to use, taken from links like `?t=1m30s`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start: Duration,
+    pub end: Option<Duration>,
+}
+impl TimeRange {
+    /// Parses `90`, `90s`, `1m30s`, `1h2m3s`, `1:30` and `1:02:03`
+    pub fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+        let timestamp = timestamp.trim();
+        if timestamp.is_empty() {
+            return None;
+        }
+
+        if timestamp.contains(':') {
+            let mut seconds = 0u64;
+            for part in timestamp.split(':') {
+                seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+            }
+            return Some(Duration::from_secs(seconds));
+        }
+
+        let mut seconds = 0u64;
+        let mut number = String::new();
+        for c in timestamp.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let factor = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return None,
+            };
+            seconds = seconds.checked_add(number.parse::<u64>().ok()?.checked_mul(factor)?)?;
+            number.clear();
+        }
+        if !number.is_empty() {
+            seconds = seconds.checked_add(number.parse().ok()?)?;
+        }
+
+        Some(Duration::from_secs(seconds))
+    }
+
+    /// Reads the range from the `t`, `start` and `end` query parameters or a `#t=` fragment
+    pub fn from_url(url: &Url) -> Option<Self> {
+        let mut start = None;
+        let mut end = None;
+
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "t" | "start" => start = Self::parse_timestamp(&value),
+                "end" => end = Self::parse_timestamp(&value),
+                _ => {}
+            }
+        }
+        if let Some(fragment) = url.fragment().and_then(|f| f.strip_prefix("t=")) {
+            start = start.or_else(|| Self::parse_timestamp(fragment));
+        }
+
+        if start.is_none() && end.is_none() {
+            return None;
+        }
+        let start = start.unwrap_or_default();
+
+        Some(TimeRange {
+            start,
+            end: end.filter(|end| *end > start),
+        })
+    }
+}
+
+/// Turns links to pages, e.g. of video platforms, into audio files
+#[async_trait]
+pub trait MediaResolver: Send + Sync {
+    /// Whether url should be resolved by this resolver instead of being downloaded directly
+    fn handles(&self, url: &Url) -> bool;
+    /// Writes the audio of url into dest, cut to range if given
+    async fn resolve(
+        &self,
+        url: &Url,
+        range: Option<TimeRange>,
+        dest: &Path,
+        size_limit: Option<u64>,
+    ) -> Result<(), ResolveError>;
+}
+
+#[cfg(feature = "yt-dlp")]
+pub use yt_dlp::YtDlpResolver;
+
+#[cfg(feature = "yt-dlp")]
+mod yt_dlp {
+    use super::*;
+    use crate::download::DownloadPolicy;
+
+    use log::{error, warn};
+    use std::path::PathBuf;
+    use tokio::process::Command;
+
+    pub struct YtDlpResolver {
+        pub binary: PathBuf,
+        /// sites handled by yt-dlp, including their subdomains
+        pub domains: Vec<String>,
+        pub timeout: Duration,
+    }
+    impl Default for YtDlpResolver {
+        fn default() -> Self {
+            YtDlpResolver {
+                binary: PathBuf::from("yt-dlp"),
+                domains: DownloadPolicy::parse_domains(
+                    "youtube.com,youtu.be,soundcloud.com,vimeo.com,bandcamp.com",
+                ),
+                timeout: Duration::from_secs(60),
+            }
+        }
+    }
+    #[async_trait]
+    impl MediaResolver for YtDlpResolver {
+        fn handles(&self, url: &Url) -> bool {
+            url.domain().is_some_and(|domain| {
+                DownloadPolicy::matches(&domain.trim_end_matches('.').to_lowercase(), &self.domains)
+            })
+        }
+
+        async fn resolve(
+            &self,
+            url: &Url,
+            range: Option<TimeRange>,
+            dest: &Path,
+            size_limit: Option<u64>,
+        ) -> Result<(), ResolveError> {
+            let mut command = Command::new(&self.binary);
+            command
+                .args([
+                    "--quiet",
+                    "--no-warnings",
+                    "--no-playlist",
+                    "--no-progress",
+                    "--force-overwrites",
+                    "--format",
+                    "bestaudio/best",
+                ])
+                .arg("--output")
+                .arg(dest)
+                .kill_on_drop(true);
+            if let Some(limit) = size_limit {
+                command.args(["--max-filesize", &limit.to_string()]);
+            }
+            if let Some(range) = range {
+                let end = range
+                    .end
+                    .map_or("inf".to_string(), |end| end.as_secs_f64().to_string());
+                command.args([
+                    "--download-sections",
+                    &format!("*{}-{end}", range.start.as_secs_f64()),
+                ]);
+            }
+            // keep url from being read as an option
+            command.arg("--").arg(url.as_str());
+
+            let output = tokio::time::timeout(self.timeout, command.output())
+                .await
+                .map_err(|_| ResolveError::Timeout)?
+                .map_err(|why| {
+                    error!("Could not run {}: {why}", self.binary.display());
+                    ResolveError::Io(why)
+                })?;
+
+            if !output.status.success() {
+                return Err(ResolveError::Unavailable(
+                    String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                ));
+            }
+
+            // yt-dlp skips files over --max-filesize without failing
+            match tokio::fs::metadata(dest).await {
+                Ok(metadata) if size_limit.is_some_and(|limit| metadata.len() > limit) => {
+                    if let Err(why) = tokio::fs::remove_file(dest).await {
+                        warn!("Could not remove {}: {}", dest.display(), why);
+                    }
+                    Err(ResolveError::TooLarge)
+                }
+                Ok(_) => Ok(()),
+                Err(_) if size_limit.is_some() => Err(ResolveError::TooLarge),
+                Err(why) => Err(ResolveError::Io(why)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Option<Duration> {
+        Some(Duration::from_secs(secs))
+    }
+
+    fn range(url: &str) -> Option<TimeRange> {
+        TimeRange::from_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parse_timestamp() {
+        assert_eq!(TimeRange::parse_timestamp("90"), secs(90));
+        assert_eq!(TimeRange::parse_timestamp("90s"), secs(90));
+        assert_eq!(TimeRange::parse_timestamp(" 1m30s "), secs(90));
+        assert_eq!(TimeRange::parse_timestamp("1h2m3s"), secs(3723));
+        assert_eq!(TimeRange::parse_timestamp("1h3s"), secs(3603));
+        assert_eq!(TimeRange::parse_timestamp("1:30"), secs(90));
+        assert_eq!(TimeRange::parse_timestamp("1:02:03"), secs(3723));
+    }
+
+    #[test]
+    fn parse_malformed_timestamp() {
+        for timestamp in [
+            "",
+            " ",
+            "abc",
+            "1x",
+            "m",
+            "1h-2m",
+            "1:",
+            "1::30",
+            ":30",
+            "1:3o",
+            "-5",
+            "99999999999999999999",
+            "9999999999999999999h",
+        ] {
+            assert_eq!(
+                TimeRange::parse_timestamp(timestamp),
+                None,
+                "'{timestamp}' was parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn range_from_url() {
+        assert_eq!(
+            range("https://youtu.be/id?t=90"),
+            Some(TimeRange {
+                start: Duration::from_secs(90),
+                end: None
+            })
+        );
+        assert_eq!(
+            range("https://www.youtube.com/watch?v=id&t=1h2m3s"),
+            Some(TimeRange {
+                start: Duration::from_secs(3723),
+                end: None
+            })
+        );
+        assert_eq!(
+            range("https://vimeo.com/id#t=1m30s"),
+            Some(TimeRange {
+                start: Duration::from_secs(90),
+                end: None
+            })
+        );
+        assert_eq!(
+            range("https://youtu.be/id?start=10&end=20"),
+            Some(TimeRange {
+                start: Duration::from_secs(10),
+                end: secs(20)
+            })
+        );
+        // the query takes precedence over the fragment
+        assert_eq!(
+            range("https://youtu.be/id?t=5#t=10"),
+            Some(TimeRange {
+                start: Duration::from_secs(5),
+                end: None
+            })
+        );
+        // an end before the start is ignored
+        assert_eq!(
+            range("https://youtu.be/id?t=20&end=10"),
+            Some(TimeRange {
+                start: Duration::from_secs(20),
+                end: None
+            })
+        );
+        assert_eq!(
+            range("https://youtu.be/id?end=10"),
+            Some(TimeRange {
+                start: Duration::ZERO,
+                end: secs(10)
+            })
+        );
+    }
+
+    #[test]
+    fn no_range_from_url() {
+        for url in [
+            "https://youtu.be/id",
+            "https://youtu.be/id?v=t",
+            "https://youtu.be/id?t=",
+            "https://youtu.be/id?t=soon",
+            "https://youtu.be/id#t=1x",
+            "https://youtu.be/id#comments",
+        ] {
+            assert_eq!(range(url), None, "range in {url}");
+        }
+    }
+}
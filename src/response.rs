@@ -0,0 +1,108 @@
+use crate::error::CommandError;
+
+use fluent_bundle::FluentArgs;
+use std::time::Duration;
+
+/// Reply to an interaction, along with the details its message shows
+#[derive(Debug)]
+pub enum Response {
+    Success,
+    ChimePendingApproval,
+    /// chime was submitted before and still awaits a decision
+    ChimeStillPending,
+    /// chime was rejected before and will not be played
+    ChimeRejected,
+    PrivacyExportSent,
+    PrivacyDeleteDone,
+    RoleForbidden {
+        role: String,
+    },
+    ModerationEnabled {
+        channel_id: u64,
+    },
+    ModerationDisabled,
+    ModerationApproved {
+        moderator_id: u64,
+    },
+    ModerationRejected {
+        moderator_id: u64,
+    },
+    CooldownsSet {
+        user_cooldown: Duration,
+        guild_plays_per_minute: u64,
+    },
+    UserMuted {
+        user_id: u64,
+    },
+    UserUnmuted {
+        user_id: u64,
+    },
+    UserChimeCleared {
+        user_id: u64,
+    },
+    AuditEmpty,
+    LanguageSet {
+        language: String,
+    },
+    LanguageReset,
+    Failed(CommandError),
+}
+impl From<CommandError> for Response {
+    fn from(why: CommandError) -> Self {
+        Response::Failed(why)
+    }
+}
+impl Response {
+    pub fn message_id(&self) -> &'static str {
+        match self {
+            Response::Success => "success",
+            Response::ChimePendingApproval => "chime-pending-approval",
+            Response::ChimeStillPending => "chime-still-pending",
+            Response::ChimeRejected => "chime-rejected",
+            Response::PrivacyExportSent => "privacy-export-sent",
+            Response::PrivacyDeleteDone => "privacy-delete-done",
+            Response::RoleForbidden { .. } => "role-forbidden",
+            Response::ModerationEnabled { .. } => "moderation-enabled",
+            Response::ModerationDisabled => "moderation-disabled",
+            Response::ModerationApproved { .. } => "moderation-approved",
+            Response::ModerationRejected { .. } => "moderation-rejected",
+            Response::CooldownsSet { .. } => "cooldowns-set",
+            Response::UserMuted { .. } => "user-muted",
+            Response::UserUnmuted { .. } => "user-unmuted",
+            Response::UserChimeCleared { .. } => "user-chime-cleared",
+            Response::AuditEmpty => "audit-empty",
+            Response::LanguageSet { .. } => "language-set",
+            Response::LanguageReset => "language-reset",
+            Response::Failed(why) => why.message_id(),
+        }
+    }
+
+    pub fn args(&self) -> Option<FluentArgs<'static>> {
+        let mut args = FluentArgs::new();
+        match self {
+            Response::RoleForbidden { role } => args.set("role", role.clone()),
+            Response::ModerationEnabled { channel_id } => {
+                args.set("channel", format!("<#{channel_id}>"))
+            }
+            Response::ModerationApproved { moderator_id }
+            | Response::ModerationRejected { moderator_id } => {
+                args.set("moderator", format!("<@{moderator_id}>"))
+            }
+            Response::CooldownsSet {
+                user_cooldown,
+                guild_plays_per_minute,
+            } => {
+                // seconds with a single decimal, e.g. 3.5
+                args.set("user", (user_cooldown.as_secs_f64() * 10.0).round() / 10.0);
+                args.set("guild", *guild_plays_per_minute);
+            }
+            Response::UserMuted { user_id }
+            | Response::UserUnmuted { user_id }
+            | Response::UserChimeCleared { user_id } => args.set("user", format!("<@{user_id}>")),
+            Response::LanguageSet { language } => args.set("language", language.clone()),
+            Response::Failed(why) => return why.args(),
+            _ => return None,
+        }
+        Some(args)
+    }
+}
@@ -0,0 +1,113 @@
+use log::{info, warn};
+use std::{
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+/// Directory for files in flight, accessible by the bot only
+pub struct TempDir {
+    path: PathBuf,
+    /// whether the directory belongs to this process only and is removed when dropped
+    unique: bool,
+}
+impl TempDir {
+    /// Creates the directory if needed and removes leftovers of previous runs
+    pub fn new(path: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&path)?;
+
+        // must not follow a link someone else placed here
+        if !fs::symlink_metadata(&path)?.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a directory", path.display()),
+            ));
+        }
+        // fails unless the directory is ours
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o700))?;
+
+        let mut removed = 0;
+        for entry in fs::read_dir(&path)?.flatten() {
+            let entry_path = entry.path();
+            let result = match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&entry_path),
+                _ => fs::remove_file(&entry_path),
+            };
+            match result {
+                Ok(_) => removed += 1,
+                Err(why) => warn!(
+                    "Could not remove leftover {}: {}",
+                    entry_path.display(),
+                    why
+                ),
+            }
+        }
+        if removed > 0 {
+            info!("Removed {removed} leftover files from {}", path.display());
+        }
+
+        Ok(TempDir {
+            path,
+            unique: false,
+        })
+    }
+
+    /// Creates a new directory in parent for this process only, so others do not remove its
+    /// files, and removes it when dropped
+    pub fn unique(parent: &Path) -> io::Result<Self> {
+        fs::create_dir_all(parent)?;
+        // fails unless the directory is ours
+        fs::set_permissions(parent, fs::Permissions::from_mode(0o700))?;
+
+        let path = parent.join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir(&path)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o700))?;
+
+        Ok(TempDir { path, unique: true })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reserves a unique path, nothing is created yet
+    pub fn file(&self) -> TempFile {
+        TempFile(self.path.join(uuid::Uuid::new_v4().to_string()))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if !self.unique {
+            return;
+        }
+        if let Err(why) = fs::remove_dir_all(&self.path) {
+            warn!(
+                "Could not remove temporary directory {}: {}",
+                self.path.display(),
+                why
+            );
+        }
+    }
+}
+
+/// Path in a TempDir whose file is removed when dropped, if it still exists
+pub struct TempFile(PathBuf);
+impl TempFile {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(why) = fs::remove_file(&self.0) {
+            if why.kind() != io::ErrorKind::NotFound {
+                warn!(
+                    "Could not remove temporary file {}: {}",
+                    self.0.display(),
+                    why
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use ffprobe::ffprobe;
+use log::{error, warn};
+use std::{
+    error::Error, fmt::Display, ops::RangeInclusive, path::Path, str::FromStr, time::Duration,
+};
+use tokio::{process::Command, task};
+
+#[derive(Debug)]
+pub enum AttachmentError {
+    Duration {
+        duration: Duration,
+        limit: Duration,
+    },
+    Unreadable,
+    Tempfile,
+    NoAudio,
+    Codec {
+        codec: String,
+        allowed: Vec<String>,
+    },
+    Channels {
+        channels: Option<i64>,
+        max: i64,
+    },
+    SampleRate {
+        rate: Option<u32>,
+        allowed: RangeInclusive<u32>,
+    },
+    Video,
+    Silent,
+}
+impl AttachmentError {
+    /// Id of the localized message describing this error
+    pub fn message_id(&self) -> &'static str {
+        match self {
+            AttachmentError::Duration { .. } => "duration-exceeded",
+            AttachmentError::Unreadable => "data-unreadable",
+            AttachmentError::Tempfile => "internal-error",
+            AttachmentError::NoAudio => "no-audio-stream",
+            AttachmentError::Codec { .. } => "unsupported-codec",
+            AttachmentError::Channels { .. } => "unsupported-channels",
+            AttachmentError::SampleRate { .. } => "unsupported-sample-rate",
+            AttachmentError::Video => "video-not-allowed",
+            AttachmentError::Silent => "chime-silent",
+        }
+    }
+}
+impl Error for AttachmentError {}
+impl Display for AttachmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttachmentError::Duration { duration, limit } => write!(
+                f,
+                "{} ({:.1}s, at most {:.1}s)",
+                self.message_id(),
+                duration.as_secs_f64(),
+                limit.as_secs_f64()
+            ),
+            AttachmentError::Codec { codec, .. } => write!(f, "{} ({codec})", self.message_id()),
+            AttachmentError::Channels { channels, max } => {
+                write!(f, "{} ({channels:?}, at most {max})", self.message_id())
+            }
+            AttachmentError::SampleRate { rate, allowed } => {
+                write!(f, "{} ({rate:?} Hz, not in {allowed:?})", self.message_id())
+            }
+            _ => write!(f, "{}", self.message_id()),
+        }
+    }
+}
+
+/// What to do with files that contain video besides audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoPolicy {
+    Reject,
+    /// keep the first audio stream only
+    Extract,
+}
+impl FromStr for VideoPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(VideoPolicy::Reject),
+            "extract" => Ok(VideoPolicy::Extract),
+            other => Err(format!("Unknown video policy '{other}'")),
+        }
+    }
+}
+
+pub struct ValidationPolicy {
+    pub duration_max: Duration,
+    /// if not empty, only these codecs are accepted, as named by ffprobe
+    pub allowed_codecs: Vec<String>,
+    pub channels_max: i64,
+    pub sample_rates: RangeInclusive<u32>,
+    pub video: VideoPolicy,
+    /// clips whose loudest sample stays below this level are rejected, `None` to accept them
+    pub silence_threshold_db: Option<f64>,
+}
+impl ValidationPolicy {
+    pub fn new(duration_max: Duration) -> Self {
+        ValidationPolicy {
+            duration_max,
+            allowed_codecs: vec![],
+            channels_max: 2,
+            sample_rates: 8000..=192000,
+            video: VideoPolicy::Reject,
+            silence_threshold_db: Some(-50.0),
+        }
+    }
+}
+
+/// Loudest sample of the first audio stream in dBFS
+async fn max_volume(path: &Path) -> Option<f64> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "info", "-nostats", "-i"])
+        .arg(path)
+        .args(["-map", "0:a:0", "-af", "volumedetect", "-f", "null", "-"])
+        .kill_on_drop(true)
+        .output()
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .find_map(|line| line.split_once("max_volume:"))
+            .and_then(|(_, volume)| volume.trim().trim_end_matches("dB").trim().parse().ok()),
+        Ok(output) => {
+            error!(
+                "Could not detect volume of {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            None
+        }
+        Err(why) => {
+            error!("Could not run ffmpeg: {:#?}", why);
+            None
+        }
+    }
+}
+
+/// Checks file against policy, returns whether it contains video
+async fn check(path: &Path, policy: &ValidationPolicy) -> Result<bool, AttachmentError> {
+    // ffprobe waits for its process, which must not block the runtime
+    let probe_path = path.to_path_buf();
+    let info = task::spawn_blocking(move || ffprobe(probe_path))
+        .await
+        .map_err(|why| {
+            error!("FFProbe task failed: {:?}", why);
+            AttachmentError::Tempfile
+        })?
+        .map_err(|why| {
+            error!("FFProbe on data failed: {:?}", why);
+            AttachmentError::Unreadable
+        })?;
+
+    let audio = info
+        .streams
+        .iter()
+        .find(|stream| stream.codec_type.as_deref() == Some("audio"))
+        .ok_or(AttachmentError::NoAudio)?;
+
+    // cover art is stored as a video stream, but is not played
+    let has_video = info.streams.iter().any(|stream| {
+        stream.codec_type.as_deref() == Some("video") && stream.disposition.attached_pic == 0
+    });
+    if has_video && policy.video == VideoPolicy::Reject {
+        return Err(AttachmentError::Video);
+    }
+
+    let codec = audio.codec_name.as_deref().unwrap_or_default();
+    if !policy.allowed_codecs.is_empty() && !policy.allowed_codecs.iter().any(|c| c == codec) {
+        return Err(AttachmentError::Codec {
+            codec: codec.to_string(),
+            allowed: policy.allowed_codecs.clone(),
+        });
+    }
+
+    if !audio
+        .channels
+        .is_some_and(|channels| (1..=policy.channels_max).contains(&channels))
+    {
+        return Err(AttachmentError::Channels {
+            channels: audio.channels,
+            max: policy.channels_max,
+        });
+    }
+
+    let rate = audio
+        .sample_rate
+        .as_deref()
+        .and_then(|rate| rate.parse::<u32>().ok());
+    if !rate.is_some_and(|rate| policy.sample_rates.contains(&rate)) {
+        return Err(AttachmentError::SampleRate {
+            rate,
+            allowed: policy.sample_rates.clone(),
+        });
+    }
+
+    match info.format.get_duration() {
+        Some(duration) if duration > policy.duration_max => {
+            return Err(AttachmentError::Duration {
+                duration,
+                limit: policy.duration_max,
+            })
+        }
+        Some(_) => {}
+        None => return Err(AttachmentError::Unreadable),
+    }
+
+    if let Some(threshold) = policy.silence_threshold_db {
+        match max_volume(path).await {
+            Some(volume) if volume < threshold => return Err(AttachmentError::Silent),
+            Some(_) => {}
+            None => return Err(AttachmentError::Unreadable),
+        }
+    }
+
+    Ok(has_video)
+}
+
+/// Checks file against policy without modifying it
+pub async fn validate_chime(path: &Path, policy: &ValidationPolicy) -> Result<(), AttachmentError> {
+    check(path, policy).await.map(|_| ())
+}
+
+/// Validates file and strips video from it in place, if the policy asks for it
+pub async fn prepare_chime(path: &Path, policy: &ValidationPolicy) -> Result<(), AttachmentError> {
+    if !check(path, policy).await? {
+        return Ok(());
+    }
+
+    let mut audio_path = path.as_os_str().to_owned();
+    audio_path.push(".audio");
+
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-y", "-i"])
+        .arg(path)
+        .args(["-map", "0:a:0", "-c:a", "copy", "-f", "matroska"])
+        .arg(&audio_path)
+        .kill_on_drop(true)
+        .output()
+        .await;
+
+    let result = match output {
+        Ok(output) if output.status.success() => {
+            tokio::fs::rename(&audio_path, path).await.map_err(|why| {
+                error!("Could not replace {}: {}", path.display(), why);
+                AttachmentError::Tempfile
+            })
+        }
+        Ok(output) => {
+            error!(
+                "Could not extract audio of {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            Err(AttachmentError::Unreadable)
+        }
+        Err(why) => {
+            error!("Could not run ffmpeg: {:#?}", why);
+            Err(AttachmentError::Tempfile)
+        }
+    };
+
+    if result.is_err() {
+        if let Err(why) = tokio::fs::remove_file(&audio_path).await {
+            if why.kind() != std::io::ErrorKind::NotFound {
+                warn!(
+                    "Could not remove {}: {}",
+                    Path::new(&audio_path).display(),
+                    why
+                );
+            }
+        }
+    }
+
+    result
+}